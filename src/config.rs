@@ -0,0 +1,419 @@
+//! Generator configuration, read from a `codegen.toml` file, or from the
+//! `[package.metadata.oscal_codegen]` section of a `Cargo.toml`.
+//!
+//! Example:
+//!
+//! ```toml
+//! emit-cargo = true
+//! strict = true
+//! dedup = true
+//!
+//! [package]
+//! name = "oscal_lib"
+//! version = "0.2.0"
+//!
+//! [oscal-types]
+//! path = "../oscal_types"
+//!
+//! [dependencies]
+//! uuid = { version = "1", features = ["serde"] }
+//!
+//! [features]
+//! extra = []
+//!
+//! [types]
+//! UUIDDatatype = "uuid::Uuid"
+//! "oscal-metadata:metadata" = "my_oscal::Metadata"
+//!
+//! [derives]
+//! global = ["Eq", "Hash"]
+//! modules = { oscal_catalog = ["schemars::JsonSchema"] }
+//! types = { Catalog = ["utoipa::ToSchema"] }
+//!
+//! [attributes]
+//! global = ["#[non_exhaustive]"]
+//!
+//! [supported-derives]
+//! "my_oscal::Measure" = ["Eq", "Hash"]
+//!
+//! [identifiers]
+//! raw = false
+//! suffix = "_"
+//! singulars = { data = "datum" }
+//! renames = { "oscal_catalog::group::part" = "group_part" }
+//! ```
+use anyhow::Result;
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use crate::{Error, Identifiers, SchemaType};
+
+/// The default config file, used if it exists and no other file is given.
+pub static DEFAULT_CONFIG_FILE: &str = "codegen.toml";
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Config {
+    /// Generate `Cargo.toml`.  If false, only `src` is generated.
+    pub emit_cargo: bool,
+    /// The `[package]` section of the generated `Cargo.toml`
+    pub package: PackageConfig,
+    /// Where the generated crate gets `oscal_types` from
+    pub oscal_types: DependencySpec,
+    /// Dependencies added to, or replacing, the default dependencies
+    pub dependencies: IndexMap<String, DependencySpec>,
+    /// The `[features]` section of the generated `Cargo.toml`, on top of
+    /// the generated model features
+    pub features: IndexMap<String, Vec<String>>,
+    /// Gate every top level module behind a Cargo feature of the same name.
+    /// Defaults to `emit-cargo`, since the features are declared in the
+    /// generated `Cargo.toml`.
+    pub model_features: Option<bool>,
+    /// Schema definitions bound to Rust types, rather than generated.  Keys
+    /// are a `$id`, `$ref`, JSON pointer or definition name.  Values are
+    /// Rust paths, such as `chrono::DateTime<chrono::FixedOffset>`.
+    pub types: IndexMap<String, String>,
+    /// Derives added to generated types, on top of the default
+    /// `Debug, Clone, PartialEq, Deserialize, Serialize`
+    pub derives: Injection,
+    /// Attributes added to generated types, such as `#[non_exhaustive]`
+    pub attributes: Injection,
+    /// Derives that types outside of the generated crate support, keyed by
+    /// Rust path.  Generated types that contain such a type skip any other
+    /// derive, unless it is one the generator knows the type supports.
+    pub supported_derives: IndexMap<String, Vec<String>>,
+    /// How schema names that aren't valid Rust identifiers are fixed up
+    pub identifiers: Identifiers,
+    /// Fail, rather than warn, on schema warnings and name collisions
+    pub strict: bool,
+    /// Generate structurally identical types once, and alias them at the
+    /// other paths
+    pub dedup: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            emit_cargo: true,
+            package: PackageConfig::default(),
+            oscal_types: DependencySpec::Detailed(DependencyDetail {
+                git: Some("https://github.com/swanky-oscal/oscal_types.git".to_owned()),
+                ..Default::default()
+            }),
+            dependencies: IndexMap::new(),
+            features: IndexMap::new(),
+            model_features: None,
+            types: IndexMap::new(),
+            derives: Injection::default(),
+            attributes: Injection::default(),
+            supported_derives: IndexMap::new(),
+            identifiers: Identifiers::default(),
+            strict: false,
+            dedup: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct PackageConfig {
+    pub name: String,
+    pub version: String,
+    pub edition: String,
+    pub authors: Vec<String>,
+    pub description: Option<String>,
+    pub keywords: Vec<String>,
+    pub repository: Option<String>,
+    pub license: Option<String>,
+}
+
+impl Default for PackageConfig {
+    fn default() -> Self {
+        Self {
+            name: "oscal_lib".to_owned(),
+            version: "0.1.1".to_owned(),
+            edition: "2021".to_owned(),
+            authors: vec!["David Skyberg <davidskyberg@gmail.com".to_owned()],
+            description: Some("OSCAL lib in Rust".to_owned()),
+            keywords: vec!["rust".to_owned(), "oscal".to_owned()],
+            repository: Some("https://github.com/dskyberg/oscal".to_owned()),
+            license: Some("MIT OR Apache 2.0".to_owned()),
+        }
+    }
+}
+
+/// Items, such as derives or attributes, added to generated types.
+/// Module keys match a module and everything below it.  Type keys are
+/// either the type name or its full path.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Injection {
+    pub global: Vec<String>,
+    pub modules: IndexMap<String, Vec<String>>,
+    pub types: IndexMap<String, Vec<String>>,
+}
+
+impl Injection {
+    /// Everything that applies to `schema_type`, from the most general to
+    /// the most specific, without duplicates.
+    pub fn for_type(&self, schema_type: &SchemaType) -> Vec<String> {
+        let mut result: Vec<String> = vec![];
+        let mut push = |items: &Vec<String>| {
+            for item in items {
+                if !result.contains(item) {
+                    result.push(item.to_owned());
+                }
+            }
+        };
+
+        push(&self.global);
+        for (prefix, items) in &self.modules {
+            let ns = &schema_type.ns;
+            if ns == prefix || ns.starts_with(&format!("{}::", prefix)) {
+                push(items);
+            }
+        }
+        for key in [schema_type.name.clone(), schema_type.path()] {
+            if let Some(items) = self.types.get(&key) {
+                push(items);
+            }
+        }
+        result
+    }
+}
+
+/// A Cargo dependency, in either the short `"1.0"` or the table form.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum DependencySpec {
+    Version(String),
+    Detailed(DependencyDetail),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct DependencyDetail {
+    pub version: Option<String>,
+    pub path: Option<String>,
+    pub git: Option<String>,
+    pub branch: Option<String>,
+    pub tag: Option<String>,
+    pub rev: Option<String>,
+    pub registry: Option<String>,
+    pub package: Option<String>,
+    pub default_features: Option<bool>,
+    pub optional: Option<bool>,
+    pub features: Vec<String>,
+}
+
+impl DependencySpec {
+    pub fn version(version: &str) -> Self {
+        Self::Version(version.to_owned())
+    }
+
+    pub fn with_features(version: &str, features: &[&str]) -> Self {
+        Self::Detailed(DependencyDetail {
+            version: Some(version.to_owned()),
+            features: features.iter().map(|f| f.to_string()).collect(),
+            ..Default::default()
+        })
+    }
+
+    /// Render the dependency as the value of a `Cargo.toml` entry
+    pub fn to_toml(&self) -> String {
+        let detail = match self {
+            Self::Version(version) => return toml_string(version),
+            Self::Detailed(detail) => detail,
+        };
+
+        let mut entries = vec![];
+        let strings = [
+            ("version", &detail.version),
+            ("path", &detail.path),
+            ("git", &detail.git),
+            ("branch", &detail.branch),
+            ("tag", &detail.tag),
+            ("rev", &detail.rev),
+            ("registry", &detail.registry),
+            ("package", &detail.package),
+        ];
+        for (key, value) in strings {
+            if let Some(value) = value {
+                entries.push(format!("{} = {}", key, toml_string(value)));
+            }
+        }
+        if let Some(default_features) = detail.default_features {
+            entries.push(format!("default-features = {}", default_features));
+        }
+        if let Some(optional) = detail.optional {
+            entries.push(format!("optional = {}", optional));
+        }
+        if !detail.features.is_empty() {
+            entries.push(format!("features = {}", toml_array(&detail.features)));
+        }
+        format!("{{ {} }}", entries.join(", "))
+    }
+}
+
+/// Quote and escape a TOML string
+pub fn toml_string(value: &str) -> String {
+    toml::Value::String(value.to_owned()).to_string()
+}
+
+/// Render a TOML key, quoting it only if needed
+pub fn toml_key(key: &str) -> String {
+    let is_bare = !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    match is_bare {
+        true => key.to_owned(),
+        false => toml_string(key),
+    }
+}
+
+/// Render an inline TOML array of strings
+pub fn toml_array(values: &[String]) -> String {
+    let values = values
+        .iter()
+        .map(|value| toml_string(value))
+        .collect::<Vec<String>>();
+    format!("[{}]", values.join(", "))
+}
+
+impl Config {
+    /// Load the config from `path`.  If `path` is a `Cargo.toml`, its
+    /// `[package.metadata.oscal_codegen]` section is used.  Otherwise the
+    /// whole file is the config.
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)?;
+        Self::parse(&content, path)
+    }
+
+    /// Parse the config read from `path`
+    fn parse(content: &str, path: &Path) -> Result<Self> {
+        let table = content.parse::<toml::Table>()?;
+
+        let metadata = table
+            .get("package")
+            .and_then(|package| package.get("metadata"))
+            .and_then(|metadata| metadata.get("oscal_codegen"));
+
+        let is_cargo = path.file_name() == Some("Cargo.toml".as_ref());
+        let config = match (metadata, is_cargo) {
+            (Some(metadata), _) => metadata.clone().try_into::<Config>()?,
+            (None, true) => return Err(Error::NoCargoConfig(path.display().to_string()).into()),
+            (None, false) => toml::Value::Table(table).try_into::<Config>()?,
+        };
+        Ok(config)
+    }
+
+    /// Load the config given on the command line.  Without one, the default
+    /// config file is used if it exists, else the built in defaults.
+    pub fn load_or_default(path: Option<&Path>) -> Result<Self> {
+        if let Some(path) = path {
+            return Self::load(path);
+        }
+        let default_path = Path::new(DEFAULT_CONFIG_FILE);
+        match default_path.is_file() {
+            true => Self::load(default_path),
+            false => Ok(Self::default()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dependency_to_toml() {
+        assert_eq!(DependencySpec::version("1.0").to_toml(), r#""1.0""#);
+        assert_eq!(
+            DependencySpec::with_features("1.0", &["derive"]).to_toml(),
+            r#"{ version = "1.0", features = ["derive"] }"#
+        );
+    }
+
+    #[test]
+    fn test_toml_key() {
+        assert_eq!(toml_key("oscal_catalog"), "oscal_catalog");
+        assert_eq!(toml_key("a.b"), r#""a.b""#);
+    }
+
+    #[test]
+    fn test_injection_for_type() {
+        let injection = Injection {
+            global: vec!["Eq".to_owned()],
+            modules: IndexMap::from([
+                ("oscal_catalog".to_owned(), vec!["Hash".to_owned()]),
+                ("oscal_cat".to_owned(), vec!["Ord".to_owned()]),
+            ]),
+            types: IndexMap::from([(
+                "Catalog".to_owned(),
+                vec!["Eq".to_owned(), "utoipa::ToSchema".to_owned()],
+            )]),
+        };
+        let catalog = SchemaType::new("oscal_catalog::catalog", "Catalog");
+        assert_eq!(
+            injection.for_type(&catalog),
+            vec!["Eq", "Hash", "utoipa::ToSchema"]
+        );
+    }
+
+    #[test]
+    fn test_parse_config() {
+        let config = r#"
+            emit-cargo = false
+            [package]
+            name = "my_oscal"
+            [oscal-types]
+            path = "../oscal_types"
+            [dependencies]
+            uuid = { version = "1", features = ["serde"] }
+        "#
+        .parse::<toml::Table>()
+        .expect("oops");
+        let config = toml::Value::Table(config)
+            .try_into::<Config>()
+            .expect("oops");
+
+        assert!(!config.emit_cargo);
+        assert_eq!(config.package.name, "my_oscal");
+        assert_eq!(config.package.edition, "2021");
+        assert_eq!(
+            config.oscal_types.to_toml(),
+            r#"{ path = "../oscal_types" }"#
+        );
+        assert_eq!(
+            config.dependencies["uuid"].to_toml(),
+            r#"{ version = "1", features = ["serde"] }"#
+        );
+    }
+
+    #[test]
+    fn test_parse_cargo_config() {
+        let manifest = r#"
+            [package]
+            name = "my_oscal"
+            version = "0.1.0"
+            [package.metadata.oscal_codegen]
+            emit-cargo = false
+        "#;
+        let config = Config::parse(manifest, Path::new("lib/Cargo.toml")).expect("oops");
+        assert!(!config.emit_cargo);
+
+        let manifest = r#"
+            [package]
+            name = "my_oscal"
+            version = "0.1.0"
+        "#;
+        let err = Config::parse(manifest, Path::new("lib/Cargo.toml")).expect_err("oops");
+        assert_eq!(
+            err.to_string(),
+            "lib/Cargo.toml has no [package.metadata.oscal_codegen] section"
+        );
+    }
+}
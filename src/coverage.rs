@@ -0,0 +1,403 @@
+//! How much of a schema the generated code honors, for the `coverage`
+//! subcommand.
+//!
+//! The raw schema JSON is walked with the parser's rules, and every keyword
+//! is counted as honored, partially honored or dropped.  What the parser
+//! left out, as reported in its [`Diagnostics`], is dropped with everything
+//! in it.  Datatypes come from `oscal_types`, and definitions bound to a
+//! Rust type by `[types]` are that type, so both are honored as a whole.
+//! Enum values and string constraints, such as `pattern`, are parsed but
+//! not enforced by the generated code, so they are dropped.
+use anyhow::Result;
+use clap::ValueEnum;
+use indexmap::IndexMap;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+use crate::{is_datatype, pointer, try_string_entry, Code, Diagnostics, Resolver};
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum CoverageFormat {
+    Text,
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Fidelity {
+    Honored,
+    Partial,
+    Dropped,
+}
+
+/// Where a schema node is, which decides what its keywords do
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Node {
+    Root,
+    /// A `oneOf` alternative of the root, naming documents
+    Alternative,
+    /// A root property, naming a document
+    Document,
+    Definition,
+    Property,
+    Items,
+    /// A branch of an `anyOf`, which is a `$ref` and its enum values
+    Branch,
+    /// In a datatype, or a definition bound to a Rust type
+    Delegated,
+    /// Left out by the parser
+    Dropped,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct KeywordCoverage {
+    pub honored: usize,
+    pub partial: usize,
+    pub dropped: usize,
+}
+
+/// A schema node with keywords that weren't fully honored
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct Location {
+    pub pointer: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub partial: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub dropped: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct Coverage {
+    pub version: String,
+    pub total: KeywordCoverage,
+    /// By keyword, in alphabetical order
+    pub keywords: BTreeMap<String, KeywordCoverage>,
+    /// The worst first
+    pub locations: Vec<Location>,
+}
+
+impl Coverage {
+    /// The coverage of `schema`, given what the parser reported about it.
+    /// `overrides` are the `[types]` of the config.
+    pub fn new(
+        schema: &Value,
+        version: &str,
+        diagnostics: &Diagnostics,
+        overrides: &IndexMap<String, String>,
+    ) -> Self {
+        let left_out = diagnostics
+            .iter()
+            .filter(|diagnostic| {
+                matches!(
+                    diagnostic.code,
+                    Code::MalformedArray
+                        | Code::UnsupportedArrayItems
+                        | Code::UnsupportedProperty
+                        | Code::DocumentWithoutRef
+                        | Code::MalformedSchema
+                )
+            })
+            .map(|diagnostic| diagnostic.pointer.clone())
+            .collect();
+        let mut walker = Walker {
+            left_out,
+            resolver: Resolver::with_overrides(overrides),
+            coverage: Self {
+                version: version.to_owned(),
+                ..Default::default()
+            },
+            locations: IndexMap::new(),
+        };
+        if let Value::Object(map) = schema {
+            walker.walk(map, "", Node::Root);
+        }
+
+        let mut coverage = walker.coverage;
+        coverage.locations = walker.locations.into_values().collect();
+        coverage.locations.sort_by_key(|location| {
+            std::cmp::Reverse((
+                location.dropped.len() + location.partial.len(),
+                location.dropped.len(),
+            ))
+        });
+        coverage
+    }
+
+    /// Render the counts, and the `top` worst locations
+    pub fn render(&self, format: CoverageFormat, top: usize) -> Result<String> {
+        let locations = &self.locations[..top.min(self.locations.len())];
+        match format {
+            CoverageFormat::Text => self.to_text(locations),
+            CoverageFormat::Json => {
+                let report = Coverage {
+                    version: self.version.clone(),
+                    total: self.total.clone(),
+                    keywords: self.keywords.clone(),
+                    locations: locations.to_vec(),
+                };
+                Ok(serde_json::to_string_pretty(&report)? + "\n")
+            }
+        }
+    }
+
+    fn to_text(&self, locations: &[Location]) -> Result<String> {
+        let mut buffer = String::new();
+        let total = &self.total;
+        writeln!(
+            buffer,
+            "{}: {} honored, {} partial, {} dropped",
+            self.version, total.honored, total.partial, total.dropped
+        )?;
+        writeln!(buffer)?;
+        writeln!(
+            buffer,
+            "{:<24}{:>8}{:>8}{:>8}",
+            "keyword", "honored", "partial", "dropped"
+        )?;
+        for (keyword, counts) in &self.keywords {
+            writeln!(
+                buffer,
+                "{:<24}{:>8}{:>8}{:>8}",
+                keyword, counts.honored, counts.partial, counts.dropped
+            )?;
+        }
+        if !locations.is_empty() {
+            writeln!(buffer)?;
+            writeln!(buffer, "Worst locations:")?;
+        }
+        for location in locations {
+            let mut what = vec![];
+            if !location.dropped.is_empty() {
+                what.push(format!("dropped {}", location.dropped.join(", ")));
+            }
+            if !location.partial.is_empty() {
+                what.push(format!("partial {}", location.partial.join(", ")));
+            }
+            let pointer = match location.pointer.is_empty() {
+                true => "/",
+                false => &location.pointer,
+            };
+            writeln!(buffer, "  {}: {}", pointer, what.join("; "))?;
+        }
+        Ok(buffer)
+    }
+}
+
+struct Walker {
+    /// The pointers of what the parser left out
+    left_out: BTreeSet<String>,
+    resolver: Resolver,
+    coverage: Coverage,
+    locations: IndexMap<String, Location>,
+}
+
+impl Walker {
+    fn walk(&mut self, map: &Map<String, Value>, node_pointer: &str, node: Node) {
+        let node = match self.left_out.contains(node_pointer) {
+            true => Node::Dropped,
+            false => node,
+        };
+        for (keyword, value) in map {
+            let fidelity = fidelity(node, keyword, value);
+            self.count(node_pointer, keyword, fidelity);
+
+            let keyword_pointer = pointer(node_pointer, keyword);
+            let inner = |inner: Node| match node {
+                Node::Delegated | Node::Dropped => node,
+                _ => match fidelity {
+                    Fidelity::Dropped => Node::Dropped,
+                    _ => inner,
+                },
+            };
+            match (keyword.as_str(), value) {
+                ("definitions", Value::Object(definitions)) => {
+                    for (name, definition) in definitions {
+                        let Value::Object(definition) = definition else {
+                            continue;
+                        };
+                        let id = try_string_entry("$id", definition).ok();
+                        let delegated = is_datatype(name)
+                            || self.resolver.apply_override(name, id.map(|id| id.as_str()));
+                        let definition_node = match delegated {
+                            true => Node::Delegated,
+                            false => Node::Definition,
+                        };
+                        let definition_pointer = pointer(&keyword_pointer, name);
+                        self.walk(definition, &definition_pointer, inner(definition_node));
+                    }
+                }
+                ("properties", Value::Object(props)) => {
+                    let prop_node = match node {
+                        Node::Root | Node::Alternative => Node::Document,
+                        _ => Node::Property,
+                    };
+                    for (name, prop) in props {
+                        if let Value::Object(prop) = prop {
+                            self.walk(prop, &pointer(&keyword_pointer, name), inner(prop_node));
+                        }
+                    }
+                }
+                ("items", Value::Object(items)) => {
+                    self.walk(items, &keyword_pointer, inner(Node::Items));
+                }
+                ("anyOf" | "allOf" | "oneOf", Value::Array(branches)) => {
+                    let branch_node = match (node, keyword.as_str()) {
+                        (Node::Root, "oneOf") => Node::Alternative,
+                        _ => Node::Branch,
+                    };
+                    for (index, branch) in branches.iter().enumerate() {
+                        if let Value::Object(branch) = branch {
+                            let branch_pointer = pointer(&keyword_pointer, &index.to_string());
+                            self.walk(branch, &branch_pointer, inner(branch_node));
+                        }
+                    }
+                }
+                (_, Value::Object(schema)) if keyword == "additionalProperties" => {
+                    self.walk(schema, &keyword_pointer, inner(Node::Dropped));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn count(&mut self, node_pointer: &str, keyword: &str, fidelity: Fidelity) {
+        let counts = self
+            .coverage
+            .keywords
+            .entry(keyword.to_owned())
+            .or_default();
+        let total = &mut self.coverage.total;
+        match fidelity {
+            Fidelity::Honored => {
+                counts.honored += 1;
+                total.honored += 1;
+                return;
+            }
+            Fidelity::Partial => {
+                counts.partial += 1;
+                total.partial += 1;
+            }
+            Fidelity::Dropped => {
+                counts.dropped += 1;
+                total.dropped += 1;
+            }
+        }
+
+        let location = self
+            .locations
+            .entry(node_pointer.to_owned())
+            .or_insert_with(|| Location {
+                pointer: node_pointer.to_owned(),
+                ..Default::default()
+            });
+        match fidelity {
+            Fidelity::Partial => location.partial.push(keyword.to_owned()),
+            _ => location.dropped.push(keyword.to_owned()),
+        }
+    }
+}
+
+/// What becomes of `keyword` in a node
+fn fidelity(node: Node, keyword: &str, value: &Value) -> Fidelity {
+    let _type = value.as_str().unwrap_or_default();
+    match (node, keyword) {
+        (Node::Delegated, _) => Fidelity::Honored,
+        (Node::Dropped, _) => Fidelity::Dropped,
+        (Node::Root, "$schema" | "$id" | "$comment" | "definitions" | "properties" | "oneOf") => {
+            Fidelity::Honored
+        }
+        (Node::Root, "type") => Fidelity::Honored,
+        (Node::Alternative, "properties" | "required") => Fidelity::Honored,
+        (Node::Document, "$ref") => Fidelity::Honored,
+        (Node::Branch, "$ref") => Fidelity::Honored,
+        (Node::Root | Node::Alternative | Node::Document | Node::Branch, _) => Fidelity::Dropped,
+
+        (_, "$id" | "$ref" | "title" | "description") => Fidelity::Honored,
+        (Node::Definition, "type") => match _type {
+            "object" | "string" => Fidelity::Honored,
+            _ => Fidelity::Partial,
+        },
+        (Node::Property, "type") => match _type {
+            "object" | "string" | "array" => Fidelity::Honored,
+            _ => Fidelity::Dropped,
+        },
+        (Node::Items, "type") => match _type {
+            "object" | "string" => Fidelity::Honored,
+            _ => Fidelity::Dropped,
+        },
+        (_, "properties" | "required") => Fidelity::Honored,
+        (Node::Property, "items") => Fidelity::Honored,
+        // Any other property is accepted, and ignored
+        (_, "additionalProperties") => match value {
+            Value::Bool(true) => Fidelity::Honored,
+            _ => Fidelity::Dropped,
+        },
+        // The `$ref` is honored, the enum values aren't enforced
+        (_, "anyOf" | "allOf") => Fidelity::Partial,
+        _ => Fidelity::Dropped,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_coverage() {
+        let schema = serde_json::json!({
+            "$schema": "http://json-schema.org/draft-07/schema#",
+            "definitions": {
+                "TokenDatatype": { "type": "string", "pattern": "^\\w+$" },
+                "oscal-catalog:group": {
+                    "type": "object",
+                    "properties": {
+                        "id": { "$ref": "#/definitions/TokenDatatype" },
+                        "class": {
+                            "anyOf": [
+                                { "$ref": "#/definitions/TokenDatatype" },
+                                { "enum": ["a", "b"] }
+                            ]
+                        },
+                        "groups": {
+                            "type": "array",
+                            "minItems": 1,
+                            "items": { "$ref": "#group" }
+                        },
+                        "weight": { "type": "number", "minimum": 0 }
+                    },
+                    "required": ["id"],
+                    "additionalProperties": false
+                }
+            }
+        });
+        let mut diagnostics = Diagnostics::new();
+        diagnostics.warn(
+            Code::UnsupportedProperty,
+            "/definitions/oscal-catalog:group/properties/weight",
+            "left out",
+        );
+
+        let coverage = Coverage::new(&schema, "1.1.2", &diagnostics, &IndexMap::new());
+        assert_eq!(coverage.keywords["pattern"].honored, 1);
+        assert_eq!(coverage.keywords["anyOf"].partial, 1);
+        assert_eq!(coverage.keywords["enum"].dropped, 1);
+        assert_eq!(coverage.keywords["minimum"].dropped, 1);
+        assert_eq!(coverage.keywords["type"].honored, 3);
+        assert_eq!(coverage.keywords["type"].dropped, 1);
+        assert_eq!(
+            coverage.locations[0],
+            Location {
+                pointer: "/definitions/oscal-catalog:group/properties/weight".to_owned(),
+                partial: vec![],
+                dropped: vec!["type".to_owned(), "minimum".to_owned()],
+            }
+        );
+
+        let text = coverage.render(CoverageFormat::Text, 1).expect("oops");
+        assert!(text.starts_with("1.1.2: 12 honored, 1 partial, 5 dropped\n"));
+        assert!(text.ends_with(
+            "Worst locations:\n  /definitions/oscal-catalog:group/properties/weight: dropped type, minimum\n"
+        ));
+    }
+}
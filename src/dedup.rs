@@ -0,0 +1,220 @@
+//! Structural deduplication, for `dedup = true`.
+//!
+//! Schemas repeat the same inline objects under different parents, and each
+//! copy would be a Rust type of its own.  Types with the same [`Shape`] are
+//! generated once, at the first path, and aliased at the others.  Shapes are
+//! compared with the types they refer to deduplicated, so the parents of
+//! identical types can be identical too.  Types configured with different
+//! `[derives]` or `[attributes]` are kept apart.
+use indexmap::IndexMap;
+use std::collections::HashMap;
+
+use crate::{Config, Resolver, Schema, SchemaType, Shape};
+
+/// The types that are the same as an earlier type, keyed by path, with the
+/// path of that type.  Types in `reexports` are left alone.
+pub fn identical_types(
+    tree: &Schema,
+    resolver: &Resolver,
+    reexports: &IndexMap<String, String>,
+    config: &Config,
+) -> IndexMap<String, String> {
+    let mut result: IndexMap<String, String> = IndexMap::new();
+    loop {
+        let target = |path: &str| {
+            let path = canonical(&result, path);
+            reexports.get(&path).cloned().unwrap_or(path)
+        };
+        let mut first: HashMap<(Shape, Vec<String>, Vec<String>), String> = HashMap::new();
+        let mut found = IndexMap::new();
+        for obj in tree.objects() {
+            let path = SchemaType::from(obj).path();
+            if reexports.contains_key(&path) || result.contains_key(&path) {
+                continue;
+            }
+            let schema_type = SchemaType::from(obj);
+            let shape = (
+                Shape::new(obj, resolver, target),
+                config.derives.for_type(&schema_type),
+                config.attributes.for_type(&schema_type),
+            );
+            match first.get(&shape) {
+                Some(target) => {
+                    found.insert(path, target.to_owned());
+                }
+                None => {
+                    first.insert(shape, path);
+                }
+            }
+        }
+        if found.is_empty() {
+            break;
+        }
+        result.extend(found);
+    }
+
+    // A type found identical in a later round may be the target of others
+    result
+        .keys()
+        .map(|path| (path.to_owned(), canonical(&result, path)))
+        .collect()
+}
+
+fn canonical(aliases: &IndexMap<String, String>, path: &str) -> String {
+    let mut path = path;
+    while let Some(target) = aliases.get(path) {
+        path = target;
+    }
+    path.to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Property, SchemaObject};
+
+    #[test]
+    fn test_identical_types() {
+        let mut resolver = Resolver::new();
+        resolver.add_type("#a-origin", SchemaType::new("a::origin", "Origin"));
+        resolver.add_type("#b-origin", SchemaType::new("b::origin", "Source"));
+        resolver.add_type("#c-origin", SchemaType::new("c::origin", "Origin"));
+        let token = "#/definitions/TokenDatatype";
+        let string = "#/definitions/StringDatatype";
+        let prop = |name: &str, _ref: &str| {
+            Some(IndexMap::from([(
+                name.to_owned(),
+                Property::test_property(name, name, _ref),
+            )]))
+        };
+
+        let mut tree = Schema::new("", "1.1.2", "");
+        for obj in [
+            SchemaObject {
+                _type: Some("object".to_owned()),
+                props: prop("actor", token),
+                ..SchemaObject::test_object("a::origin", "Origin")
+            },
+            SchemaObject {
+                _type: Some("object".to_owned()),
+                props: prop("origin", "#a-origin"),
+                ..SchemaObject::test_object("a", "A")
+            },
+            // Same fields under another name, and a parent the same as A
+            SchemaObject {
+                _type: Some("object".to_owned()),
+                props: prop("actor", token),
+                ..SchemaObject::test_object("b::origin", "Source")
+            },
+            SchemaObject {
+                _type: Some("object".to_owned()),
+                props: prop("origin", "#b-origin"),
+                ..SchemaObject::test_object("b", "B")
+            },
+            // Same field names with another type
+            SchemaObject {
+                _type: Some("object".to_owned()),
+                props: prop("actor", string),
+                ..SchemaObject::test_object("c::origin", "Origin")
+            },
+            SchemaObject {
+                _type: Some("object".to_owned()),
+                props: prop("origin", "#c-origin"),
+                ..SchemaObject::test_object("c", "C")
+            },
+        ] {
+            tree.add_object(&obj.ns.clone(), &obj).expect("oops");
+        }
+
+        let aliases = identical_types(&tree, &resolver, &IndexMap::new(), &Config::default());
+        assert_eq!(
+            aliases.into_iter().collect::<Vec<_>>(),
+            vec![
+                (
+                    "b::origin::Source".to_owned(),
+                    "a::origin::Origin".to_owned()
+                ),
+                ("b::B".to_owned(), "a::A".to_owned()),
+            ]
+        );
+
+        let reexports = IndexMap::from([("b::B".to_owned(), "a::A".to_owned())]);
+        assert_eq!(
+            identical_types(&tree, &resolver, &reexports, &Config::default()).len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_string_constraints_differ() {
+        let resolver = Resolver::new();
+        let mut tree = Schema::new("", "1.1.2", "");
+        for (name, pattern) in [("A", "^a$"), ("B", "^b$"), ("C", "^a$")] {
+            let map = serde_json::json!({"type": "string", "pattern": pattern});
+            let obj =
+                SchemaObject::parse(&name.to_lowercase(), name, map.as_object().expect("oops"))
+                    .expect("oops");
+            tree.add_object(&obj.ns.clone(), &obj).expect("oops");
+        }
+
+        let aliases = identical_types(&tree, &resolver, &IndexMap::new(), &Config::default());
+        assert_eq!(
+            aliases.into_iter().collect::<Vec<_>>(),
+            vec![("c::C".to_owned(), "a::A".to_owned())]
+        );
+    }
+
+    #[test]
+    fn test_decorations_differ() {
+        let resolver = Resolver::new();
+        let token = "#/definitions/TokenDatatype";
+        let prop = |name: &str, _ref: &str| {
+            Some(IndexMap::from([(
+                name.to_owned(),
+                Property::test_property(name, name, _ref),
+            )]))
+        };
+        let mut tree = Schema::new("", "1.1.2", "");
+        for obj in [
+            SchemaObject {
+                _type: Some("object".to_owned()),
+                props: prop("actor", token),
+                ..SchemaObject::test_object("a", "A")
+            },
+            SchemaObject {
+                _type: Some("object".to_owned()),
+                props: prop("actor", token),
+                ..SchemaObject::test_object("b", "B")
+            },
+            SchemaObject {
+                _type: Some("object".to_owned()),
+                props: prop("actor", token),
+                ..SchemaObject::test_object("c", "C")
+            },
+        ] {
+            tree.add_object(&obj.ns.clone(), &obj).expect("oops");
+        }
+
+        let mut config = Config::default();
+        config
+            .derives
+            .types
+            .insert("B".to_owned(), vec!["Eq".to_owned()]);
+        config
+            .attributes
+            .modules
+            .insert("c".to_owned(), vec!["#[non_exhaustive]".to_owned()]);
+        assert!(identical_types(&tree, &resolver, &IndexMap::new(), &config).is_empty());
+
+        config.derives.global.push("Hash".to_owned());
+        config
+            .attributes
+            .types
+            .insert("a::A".to_owned(), vec!["#[non_exhaustive]".to_owned()]);
+        let aliases = identical_types(&tree, &resolver, &IndexMap::new(), &config);
+        assert_eq!(
+            aliases.into_iter().collect::<Vec<_>>(),
+            vec![("c::C".to_owned(), "a::A".to_owned())]
+        );
+    }
+}
@@ -0,0 +1,260 @@
+//! Problems found in a schema.  Parsing goes on past them, collecting
+//! [`Diagnostic`]s in a [`Diagnostics`] sink, which is reported once the
+//! schema is parsed.  Each one is located by a JSON pointer into the
+//! schema, such as `/definitions/oscal-complete-oscal-catalog:group`.
+//! Generation reports what it finds the same way.
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::Serialize;
+use std::path::Path;
+
+use crate::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum DiagnosticsFormat {
+    Text,
+    /// One JSON object per line
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    /// Worth knowing, but never an error, even in strict mode
+    Note,
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Code {
+    /// Array items without a `$ref`, `anyOf` or `type`
+    MalformedArray,
+    /// Array items of a type other than object or string
+    UnsupportedArrayItems,
+    /// A property without a `$ref`, `anyOf` or supported type
+    UnsupportedProperty,
+    /// A root document property without a `$ref`
+    DocumentWithoutRef,
+    /// A `$ref` that matches no definition
+    UnresolvedRef,
+    /// A `[types]` override that matched no definition
+    UnusedOverride,
+    /// JSON that can't be parsed as the schema item it should be, such as
+    /// a property that isn't an object
+    MalformedSchema,
+    /// Types that hold each other, and the fields boxed because of it
+    BoxedCycle,
+    /// Two names that would have been the same, and what one was renamed to
+    NameCollision,
+}
+
+impl std::fmt::Display for Code {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let code = serde_json::to_value(self).map_err(|_| std::fmt::Error)?;
+        write!(f, "{}", code.as_str().unwrap_or_default())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: Code,
+    /// The schema file, if there is one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    /// Where in the schema, or empty for the schema as a whole
+    pub pointer: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let severity = match self.severity {
+            Severity::Note => "note",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        let pointer = match self.pointer.is_empty() {
+            true => "/",
+            false => &self.pointer,
+        };
+        let location = match &self.file {
+            Some(file) => format!("{}#{}", file, pointer),
+            None => pointer.to_owned(),
+        };
+        write!(
+            f,
+            "{}[{}] {}: {}",
+            severity, self.code, location, self.message
+        )
+    }
+}
+
+/// The sink the parser reports to
+#[derive(Debug, Default)]
+pub struct Diagnostics {
+    diagnostics: Vec<Diagnostic>,
+    /// The schema file being parsed
+    file: Option<String>,
+}
+
+impl Diagnostics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record `file` as the schema of what is reported from now on
+    pub fn set_file(&mut self, file: &Path) {
+        self.file = Some(file.display().to_string());
+    }
+
+    pub fn note(&mut self, code: Code, pointer: &str, message: &str) {
+        self.push(Severity::Note, code, pointer, message);
+    }
+
+    pub fn warn(&mut self, code: Code, pointer: &str, message: &str) {
+        self.push(Severity::Warning, code, pointer, message);
+    }
+
+    pub fn error(&mut self, code: Code, pointer: &str, message: &str) {
+        self.push(Severity::Error, code, pointer, message);
+    }
+
+    /// The value of `result`, or nothing if it failed, reporting the
+    /// failure as a malformed schema at `pointer`
+    pub fn check<T>(&mut self, result: Result<T>, pointer: &str) -> Option<T> {
+        match result {
+            Ok(value) => Some(value),
+            Err(err) => {
+                self.error(Code::MalformedSchema, pointer, &err.to_string());
+                None
+            }
+        }
+    }
+
+    fn push(&mut self, severity: Severity, code: Code, pointer: &str, message: &str) {
+        self.diagnostics.push(Diagnostic {
+            severity,
+            code,
+            file: self.file.clone(),
+            pointer: pointer.to_owned(),
+            message: message.to_owned(),
+        });
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Diagnostic> {
+        self.diagnostics.iter()
+    }
+
+    pub fn render(&self, format: DiagnosticsFormat) -> Result<String> {
+        let mut result = String::new();
+        for diagnostic in &self.diagnostics {
+            let line = match format {
+                DiagnosticsFormat::Text => diagnostic.to_string(),
+                DiagnosticsFormat::Json => serde_json::to_string(diagnostic)?,
+            };
+            result.push_str(&line);
+            result.push('\n');
+        }
+        Ok(result)
+    }
+
+    /// Print everything collected to stderr, and fail if there were errors.
+    /// In strict mode, warnings are errors.
+    pub fn report(mut self, format: DiagnosticsFormat, strict: bool) -> Result<()> {
+        if strict {
+            for diagnostic in &mut self.diagnostics {
+                if diagnostic.severity == Severity::Warning {
+                    diagnostic.severity = Severity::Error;
+                }
+            }
+        }
+        eprint!("{}", self.render(format)?);
+        match self
+            .iter()
+            .filter(|d| d.severity == Severity::Error)
+            .count()
+        {
+            0 => Ok(()),
+            errors => Err(Error::SchemaErrors(errors).into()),
+        }
+    }
+}
+
+/// `pointer` extended by a reference token, escaped as JSON pointers need
+pub fn pointer(pointer: &str, token: &str) -> String {
+    format!(
+        "{}/{}",
+        pointer,
+        token.replace('~', "~0").replace('/', "~1")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diagnostics() {
+        let definition = pointer("/definitions", "oscal-complete-oscal-catalog:group");
+        let items = pointer(&pointer(&definition, "properties"), "a/b~c");
+        assert_eq!(
+            items,
+            "/definitions/oscal-complete-oscal-catalog:group/properties/a~1b~0c"
+        );
+
+        let mut diagnostics = Diagnostics::new();
+        diagnostics.warn(Code::UnsupportedProperty, &items, "left out");
+        assert_eq!(
+            diagnostics.render(DiagnosticsFormat::Text).expect("oops"),
+            format!("warning[unsupported-property] {}: left out\n", items)
+        );
+        assert_eq!(
+            diagnostics.render(DiagnosticsFormat::Json).expect("oops"),
+            format!(
+                r#"{{"severity":"warning","code":"unsupported-property","pointer":"{}","message":"left out"}}"#,
+                items
+            ) + "\n"
+        );
+        diagnostics.set_file(Path::new("schema.json"));
+        diagnostics.error(Code::MalformedArray, "", "no type");
+        assert_eq!(
+            diagnostics.iter().last().expect("oops").to_string(),
+            "error[malformed-array] schema.json#/: no type"
+        );
+        assert!(diagnostics.report(DiagnosticsFormat::Text, false).is_err());
+    }
+
+    #[test]
+    fn test_strict() {
+        let mut diagnostics = Diagnostics::new();
+        diagnostics.note(Code::BoxedCycle, "", "boxed");
+        assert_eq!(
+            diagnostics.iter().last().expect("oops").to_string(),
+            "note[boxed-cycle] /: boxed"
+        );
+        assert!(diagnostics.report(DiagnosticsFormat::Text, true).is_ok());
+
+        let mut diagnostics = Diagnostics::new();
+        diagnostics.warn(Code::NameCollision, "", "renamed");
+        assert!(diagnostics.report(DiagnosticsFormat::Text, true).is_err());
+    }
+
+    #[test]
+    fn test_check() {
+        let mut diagnostics = Diagnostics::new();
+        let value = serde_json::json!(["not", "an", "object"]);
+        assert!(diagnostics
+            .check(crate::value_to_map(&value), "/properties/a")
+            .is_none());
+        assert_eq!(diagnostics.check(Ok(1), "/properties/b"), Some(1));
+        assert_eq!(
+            diagnostics.render(DiagnosticsFormat::Json).expect("oops"),
+            r#"{"severity":"error","code":"malformed-schema","pointer":"/properties/a","message":"Object expected"}"#
+                .to_owned()
+                + "\n"
+        );
+    }
+}
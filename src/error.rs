@@ -29,4 +29,16 @@ pub enum Error {
     IncompleteNamespace(String, String),
     #[error("Failed to parse version from schema $id")]
     VersionParse,
+    #[error("No type named {0}")]
+    UnknownType(String),
+    #[error("Type name {0} is ambiguous, give one of: {1}")]
+    AmbiguousType(String, String),
+    #[error("Schema version {0} given more than once")]
+    DuplicateVersion(String),
+    #[error("Recursive newtypes, which can't be boxed: {0}")]
+    NewtypeCycle(String),
+    #[error("{0} has no [package.metadata.oscal_codegen] section")]
+    NoCargoConfig(String),
+    #[error("{0} error(s) in the schema")]
+    SchemaErrors(usize),
 }
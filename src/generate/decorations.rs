@@ -0,0 +1,283 @@
+//! The derives and attributes of every generated type.
+//!
+//! Configured derives are only kept if every field type supports them.
+//! Support is propagated through the [`TypeGraph`]: a generated type supports
+//! a derive if it derives it, and a leaf type supports it only if it is
+//! listed as supported, either below or in the config `supported-derives`.
+use indexmap::IndexMap;
+
+use crate::{is_datatype, Config, SchemaType, TypeEdge, TypeGraph};
+
+/// Derived by every generated type.
+pub static DEFAULT_DERIVES: [&str; 5] = ["Debug", "Clone", "PartialEq", "Deserialize", "Serialize"];
+
+/// Derives beyond [`DEFAULT_DERIVES`] that well known leaf types support.
+/// Leaf types and derives not listed here are assumed unsupported.
+fn builtin_supported(path: &str) -> &'static [&'static str] {
+    match path {
+        "bool" | "u8" | "u16" | "u32" | "u64" | "i8" | "i16" | "i32" | "i64" => {
+            &["Eq", "Hash", "PartialOrd", "Ord", "Default", "Copy"]
+        }
+        "f32" | "f64" => &["PartialOrd", "Default", "Copy"],
+        "String" => &["Eq", "Hash", "PartialOrd", "Ord", "Default"],
+        "serde_json::Value" => &["Eq", "Default"],
+        path => match path.strip_prefix("oscal_types::") {
+            // The schema datatypes wrap strings, integers, booleans, dates
+            // and UUIDs, which are all Eq and Hash
+            Some(name) if is_datatype(name) => &["Eq", "Hash"],
+            _ => &[],
+        },
+    }
+}
+
+/// The last path segment of a derive, such as `Hash` for `std::hash::Hash`
+fn derive_name(derive: &str) -> &str {
+    derive.rsplit("::").next().unwrap_or(derive)
+}
+
+/// Normalize an attribute to the `#[...]` form
+fn attribute(attr: &str) -> String {
+    let attr = attr.trim();
+    match attr.starts_with("#[") {
+        true => attr.to_owned(),
+        false => format!("#[{}]", attr),
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Decoration {
+    pub derives: Vec<String>,
+    pub attributes: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Decorations {
+    types: IndexMap<String, Decoration>,
+}
+
+impl Decorations {
+    pub fn new(graph: &TypeGraph, config: &Config) -> Self {
+        let mut types = IndexMap::new();
+        for (path, node) in graph.iter() {
+            let mut derives: Vec<String> = DEFAULT_DERIVES.iter().map(|d| d.to_string()).collect();
+            for derive in config.derives.for_type(&node.schema_type) {
+                if !derives
+                    .iter()
+                    .any(|d| derive_name(d) == derive_name(&derive))
+                {
+                    derives.push(derive);
+                }
+            }
+            let attributes = config
+                .attributes
+                .for_type(&node.schema_type)
+                .iter()
+                .map(|attr| attribute(attr))
+                .collect();
+            types.insert(
+                path.to_owned(),
+                Decoration {
+                    derives,
+                    attributes,
+                },
+            );
+        }
+
+        let mut result = Self { types };
+        result.remove_unsupported(graph, config);
+        result
+    }
+
+    /// Drop derives that a field type does not support, until nothing
+    /// changes.  Recursive types keep a derive unless something in the
+    /// cycle rules it out.
+    fn remove_unsupported(&mut self, graph: &TypeGraph, config: &Config) {
+        loop {
+            let mut changed = false;
+            for (path, node) in graph.iter() {
+                let derives = &self.types[path].derives;
+                let unsupported = derives
+                    .iter()
+                    .filter(|derive| !DEFAULT_DERIVES.contains(&derive.as_str()))
+                    .filter(|derive| {
+                        node.edges
+                            .iter()
+                            .any(|edge| !self.supports(edge, derive_name(derive), config))
+                    })
+                    .cloned()
+                    .collect::<Vec<String>>();
+                if unsupported.is_empty() {
+                    continue;
+                }
+                changed = true;
+                self.types[path]
+                    .derives
+                    .retain(|derive| !unsupported.contains(derive));
+            }
+            if !changed {
+                break;
+            }
+        }
+    }
+
+    /// Does the field type of `edge` allow the containing type to derive `name`
+    fn supports(&self, edge: &TypeEdge, name: &str, config: &Config) -> bool {
+        match name {
+            "Copy" if edge.array || edge.boxed => return false,
+            // Option and Vec are Default, whatever they contain
+            "Default" if edge.optional || edge.array => return true,
+            _ => {}
+        }
+
+        if let Some(target) = self.types.get(&edge.target) {
+            return target.derives.iter().any(|d| derive_name(d) == name);
+        }
+
+        let configured = config
+            .supported_derives
+            .get(&edge.target)
+            .map(|supported| supported.iter().any(|d| derive_name(d) == name))
+            .unwrap_or(false);
+        configured || builtin_supported(&edge.target).contains(&name)
+    }
+
+    pub fn get(&self, schema_type: &SchemaType) -> Decoration {
+        match self.types.get(&schema_type.path()) {
+            Some(decoration) => decoration.clone(),
+            None => Decoration {
+                derives: DEFAULT_DERIVES.iter().map(|d| d.to_string()).collect(),
+                attributes: vec![],
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Injection, Resolver, Schema, SchemaObject};
+
+    #[test]
+    fn test_float_removes_eq_and_hash() {
+        let mut resolver = Resolver::new();
+        resolver.add_type("#measure", SchemaType::external("f64"));
+        resolver.add_type("#token", SchemaType::new("oscal_types", "TokenDatatype"));
+
+        let mut schema = Schema::new("", "", "");
+        schema
+            .add_object(
+                "measure",
+                &SchemaObject {
+                    type_ref: Some("#measure".to_owned()),
+                    ..SchemaObject::test_object("measure", "Measure")
+                },
+            )
+            .expect("oops");
+        schema
+            .add_object(
+                "token",
+                &SchemaObject {
+                    type_ref: Some("#token".to_owned()),
+                    ..SchemaObject::test_object("token", "Token")
+                },
+            )
+            .expect("oops");
+
+        let config = Config {
+            derives: Injection {
+                global: vec!["Eq".to_owned(), "std::hash::Hash".to_owned()],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let graph = TypeGraph::build(&schema, &resolver);
+        let decorations = Decorations::new(&graph, &config);
+
+        let measure = decorations.get(&SchemaType::new("measure", "Measure"));
+        assert_eq!(measure.derives, DEFAULT_DERIVES.to_vec());
+
+        let token = decorations.get(&SchemaType::new("token", "Token"));
+        assert!(token.derives.contains(&"Eq".to_owned()));
+        assert!(token.derives.contains(&"std::hash::Hash".to_owned()));
+    }
+
+    #[test]
+    fn test_supported_derives() {
+        let mut resolver = Resolver::new();
+        resolver.add_type("#measure", SchemaType::external("my_oscal::Measure"));
+        resolver.add_type("#stamp", SchemaType::new("oscal_types", "DateTimeDatatype"));
+
+        let mut schema = Schema::new("", "", "");
+        schema
+            .add_object(
+                "measure",
+                &SchemaObject {
+                    type_ref: Some("#measure".to_owned()),
+                    ..SchemaObject::test_object("measure", "Measure")
+                },
+            )
+            .expect("oops");
+        schema
+            .add_object(
+                "stamp",
+                &SchemaObject {
+                    type_ref: Some("#stamp".to_owned()),
+                    ..SchemaObject::test_object("stamp", "Stamp")
+                },
+            )
+            .expect("oops");
+
+        let config = Config {
+            derives: Injection {
+                global: vec!["Eq".to_owned(), "Hash".to_owned()],
+                ..Default::default()
+            },
+            supported_derives: IndexMap::from([(
+                "my_oscal::Measure".to_owned(),
+                vec!["Eq".to_owned()],
+            )]),
+            ..Default::default()
+        };
+        let graph = TypeGraph::build(&schema, &resolver);
+        let decorations = Decorations::new(&graph, &config);
+
+        let measure = decorations.get(&SchemaType::new("measure", "Measure"));
+        assert!(measure.derives.contains(&"Eq".to_owned()));
+        assert!(!measure.derives.contains(&"Hash".to_owned()));
+
+        // Not a schema datatype, so nothing beyond the defaults is assumed
+        let stamp = decorations.get(&SchemaType::new("stamp", "Stamp"));
+        assert_eq!(stamp.derives, DEFAULT_DERIVES.to_vec());
+    }
+
+    #[test]
+    fn test_unknown_derives_dropped_from_datatypes() {
+        let mut resolver = Resolver::new();
+        resolver.add_type("#token", SchemaType::new("oscal_types", "TokenDatatype"));
+
+        let mut schema = Schema::new("", "", "");
+        schema
+            .add_object(
+                "token",
+                &SchemaObject {
+                    type_ref: Some("#token".to_owned()),
+                    ..SchemaObject::test_object("token", "Token")
+                },
+            )
+            .expect("oops");
+
+        let config = Config {
+            derives: Injection {
+                global: vec!["Eq".to_owned(), "schemars::JsonSchema".to_owned()],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let graph = TypeGraph::build(&schema, &resolver);
+        let decorations = Decorations::new(&graph, &config);
+
+        let token = decorations.get(&SchemaType::new("token", "Token"));
+        assert!(token.derives.contains(&"Eq".to_owned()));
+        assert!(!token.derives.contains(&"schemars::JsonSchema".to_owned()));
+    }
+}
@@ -0,0 +1,219 @@
+//! Rustdoc for the generated code.
+//!
+//! Schema text is escaped so that it renders as the plain text it is, and
+//! wrapped so that no line, comment prefix included, is wider than
+//! [`MAX_WIDTH`].  Everything the schema says about where an item came from
+//! goes in a trailing `# Schema` section.
+
+/// The widest a generated doc comment line may be
+pub const MAX_WIDTH: usize = 80;
+
+/// A doc comment: paragraphs of schema text, then the `# Schema` section
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DocComment {
+    paragraphs: Vec<String>,
+    schema: Vec<String>,
+}
+
+impl DocComment {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add schema text, escaped.  Blank lines start a new paragraph, other
+    /// line breaks are treated as spaces.
+    pub fn text(&mut self, text: &str) -> &mut Self {
+        let mut paragraph = vec![];
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() {
+                self.push_paragraph(&mut paragraph);
+            } else {
+                paragraph.push(line);
+            }
+        }
+        self.push_paragraph(&mut paragraph);
+        self
+    }
+
+    fn push_paragraph(&mut self, lines: &mut Vec<&str>) {
+        if !lines.is_empty() {
+            self.paragraphs.push(escape(&lines.join(" ")));
+            lines.clear();
+        }
+    }
+
+    /// Add a paragraph of markdown written by the generator, unescaped
+    pub fn markdown(&mut self, markdown: &str) -> &mut Self {
+        self.paragraphs.push(markdown.to_owned());
+        self
+    }
+
+    /// Add a `# Schema` entry.  The value is markdown, typically built with
+    /// [`code`] or [`link`].
+    pub fn schema(&mut self, label: &str, value: &str) -> &mut Self {
+        self.schema.push(format!("- {}: {}", label, value));
+        self
+    }
+
+    /// Render the comment, with every line starting with `prefix`, such as
+    /// `///`, `    ///` or `//!`.  Empty comments render as nothing.
+    pub fn render(&self, prefix: &str) -> String {
+        // Leave room for the space after the prefix, and for escaping a
+        // character at the start of a line
+        let width = MAX_WIDTH.saturating_sub(prefix.len() + 2);
+        let mut blocks: Vec<Vec<String>> = self
+            .paragraphs
+            .iter()
+            .map(|paragraph| wrap(paragraph, width))
+            .collect();
+        if !self.schema.is_empty() {
+            blocks.push(vec!["# Schema".to_owned()]);
+            blocks.push(self.schema.clone());
+        }
+
+        let mut result = String::new();
+        for (i, block) in blocks.iter().enumerate() {
+            if i > 0 {
+                result.push_str(&format!("{}\n", prefix));
+            }
+            for line in block {
+                result.push_str(&format!("{} {}\n", prefix, line));
+            }
+        }
+        result
+    }
+}
+
+/// Escape the markdown and HTML in schema text.  Bare URLs become
+/// autolinks.
+pub fn escape(text: &str) -> String {
+    text.split(' ')
+        .map(escape_word)
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+fn escape_word(word: &str) -> String {
+    if word.starts_with("https://") || word.starts_with("http://") {
+        let url = word.trim_end_matches(['.', ',', ';', ':', ')']);
+        if !url.contains(['<', '>']) {
+            return format!("<{}>{}", url, escape_word(&word[url.len()..]));
+        }
+    }
+
+    let mut result = String::new();
+    for c in word.chars() {
+        if matches!(
+            c,
+            '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '&' | '#' | '|' | '~'
+        ) {
+            result.push('\\');
+        }
+        result.push(c);
+    }
+    result
+}
+
+/// Wrap markdown at `width` columns.  A word wider than `width` gets a
+/// line of its own.  Lines that would start a list, heading or quote are
+/// escaped.
+pub fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut result: Vec<String> = vec![];
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        if !line.is_empty() && line.chars().count() + 1 + word.chars().count() > width {
+            result.push(std::mem::take(&mut line));
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    if !line.is_empty() {
+        result.push(line);
+    }
+
+    result
+        .into_iter()
+        .map(|line| match starts_block(&line) {
+            true => format!("\\{}", line),
+            false => line,
+        })
+        .collect()
+}
+
+/// Would markdown read a line starting like this as something other than
+/// paragraph text
+fn starts_block(line: &str) -> bool {
+    if line.starts_with(['-', '+', '=', '>']) {
+        return true;
+    }
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    digits > 0 && line[digits..].starts_with(['.', ')'])
+}
+
+/// Inline code
+pub fn code(text: &str) -> String {
+    match text.contains('`') {
+        true => format!("`` {} ``", text),
+        false => format!("`{}`", text),
+    }
+}
+
+/// An intra-doc link to a type in scope, such as ``[`Metadata`]``.  Generic
+/// arguments are left out of the link.
+pub fn link(type_name: &str) -> String {
+    let base = type_name.split('<').next().unwrap_or(type_name);
+    format!("[`{}`]", base)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wrap() {
+        let text = "word ".repeat(40);
+        let lines = wrap(&text, 20);
+        assert!(lines.iter().all(|line| line.len() <= 20));
+        assert_eq!(lines.join(" "), text.trim());
+
+        let long = "x".repeat(30);
+        assert_eq!(wrap(&format!("a {} b", long), 20), vec!["a", &long, "b"]);
+        assert_eq!(wrap("word 1. a - b", 6), vec!["word", "\\1. a -", "b"]);
+        assert_eq!(wrap("word - a", 4), vec!["word", "\\- a"]);
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("a <b> [c] *d* e_f"), r"a \<b\> \[c\] \*d\* e\_f");
+        assert_eq!(
+            escape("See https://pages.nist.gov/OSCAL/."),
+            "See <https://pages.nist.gov/OSCAL/>."
+        );
+    }
+
+    #[test]
+    fn test_render() {
+        let mut doc = DocComment::new();
+        doc.text("Title")
+            .text("First line\nsame paragraph.\n\nSecond paragraph.")
+            .schema("JSON property", &code("last-modified"))
+            .schema("Type", &link("DateTime<FixedOffset>"));
+        assert_eq!(
+            doc.render("    ///"),
+            r"    /// Title
+    ///
+    /// First line same paragraph.
+    ///
+    /// Second paragraph.
+    ///
+    /// # Schema
+    ///
+    /// - JSON property: `last-modified`
+    /// - Type: [`DateTime`]
+"
+        );
+        assert_eq!(DocComment::new().render("///"), "");
+    }
+}
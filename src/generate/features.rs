@@ -0,0 +1,116 @@
+//! One Cargo feature per top level module of the generated crate.
+//!
+//! Each feature enables the module and every module its types refer to, so
+//! a tool that only reads catalogs only compiles what catalogs need.
+//!
+//! With several schema versions, there is one feature per version instead.
+use indexmap::IndexMap;
+
+use crate::{Schema, TypeGraph};
+
+/// Enables every module
+pub static FULL_FEATURE: &str = "full";
+
+/// The top level module of a type or module path
+fn top_module(path: &str) -> &str {
+    path.split("::").next().unwrap_or(path)
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ModelFeatures {
+    /// Every top level module, with the other modules it needs, transitively
+    modules: IndexMap<String, Vec<String>>,
+}
+
+impl ModelFeatures {
+    pub fn new(tree: &Schema, graph: &TypeGraph, reexports: &IndexMap<String, String>) -> Self {
+        // Direct module dependencies, in tree order
+        let mut direct: IndexMap<String, Vec<String>> = tree
+            .keys()
+            .map(|module| (module.to_owned(), vec![]))
+            .collect();
+        for (_, node) in graph.iter() {
+            let from = top_module(&node.schema_type.ns);
+            for edge in &node.edges {
+                let Some(target) = graph.get(&edge.target) else {
+                    // Not a generated type
+                    continue;
+                };
+                add_dependency(&mut direct, from, top_module(&target.schema_type.ns));
+            }
+        }
+        // A re-exported type needs the module it is re-exported from
+        for (path, target) in reexports {
+            add_dependency(&mut direct, top_module(path), top_module(target));
+        }
+
+        let modules = direct
+            .keys()
+            .map(|module| (module.to_owned(), closure(module, &direct)))
+            .collect();
+        Self { modules }
+    }
+
+    /// The `#[cfg(...)]` that gates the module containing `path`
+    pub fn cfg(&self, path: &str) -> String {
+        format!(r#"#[cfg(feature = "{}")]"#, top_module(path))
+    }
+
+    /// The `[features]` of the generated `Cargo.toml`
+    pub fn cargo_features(&self) -> IndexMap<String, Vec<String>> {
+        let mut result = IndexMap::new();
+        result.insert("default".to_owned(), vec![FULL_FEATURE.to_owned()]);
+        result.insert(
+            FULL_FEATURE.to_owned(),
+            self.modules.keys().cloned().collect(),
+        );
+        for (module, deps) in &self.modules {
+            result.insert(module.to_owned(), deps.clone());
+        }
+        result
+    }
+}
+
+fn add_dependency(direct: &mut IndexMap<String, Vec<String>>, from: &str, to: &str) {
+    if from == to || !direct.contains_key(to) {
+        return;
+    }
+    if let Some(deps) = direct.get_mut(from) {
+        if !deps.iter().any(|dep| dep == to) {
+            deps.push(to.to_owned());
+        }
+    }
+}
+
+/// Every module reachable from `module`, excluding itself, in the order found
+fn closure(module: &str, direct: &IndexMap<String, Vec<String>>) -> Vec<String> {
+    let mut result: Vec<String> = vec![];
+    let mut stack = vec![module];
+    while let Some(next) = stack.pop() {
+        for dep in direct.get(next).into_iter().flatten() {
+            if dep != module && !result.contains(dep) {
+                result.push(dep.to_owned());
+                stack.push(dep);
+            }
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_closure() {
+        let direct = IndexMap::from([
+            ("catalog".to_owned(), vec!["control".to_owned()]),
+            ("control".to_owned(), vec!["metadata".to_owned()]),
+            ("metadata".to_owned(), vec!["control".to_owned()]),
+            ("ssp".to_owned(), vec![]),
+        ]);
+        assert_eq!(closure("catalog", &direct), vec!["control", "metadata"]);
+        assert_eq!(closure("metadata", &direct), vec!["control"]);
+        assert!(closure("ssp", &direct).is_empty());
+    }
+}
@@ -1,52 +1,222 @@
 use anyhow::Result;
-use std::fs::File;
-use std::io::prelude::*;
-use std::path::{Path, PathBuf};
+use indexmap::IndexMap;
+use std::fmt::Write;
+use std::path::Path;
 
-pub fn generate_cargo(path: PathBuf) -> Result<()> {
-    let cargo_path = Path::new(&path).join("Cargo.toml").to_path_buf();
+use crate::{toml_array, toml_key, toml_string, Config, DependencySpec};
 
-    let mut buffer = File::create(cargo_path)?;
+use super::{ModelFeatures, Output};
 
+/// The first line of a generated `Cargo.toml`.  It tells a generated
+/// manifest from a hand-written one when pruning.
+pub(super) static CARGO_HEADER: &str = "# Generated by oscal_codegen";
+
+/// The dependencies every generated crate needs
+fn default_dependencies() -> IndexMap<String, DependencySpec> {
+    IndexMap::from([
+        (
+            "chrono".to_owned(),
+            DependencySpec::with_features("0.4", &["serde", "alloc", "now"]),
+        ),
+        (
+            "semver".to_owned(),
+            DependencySpec::with_features("1.0.18", &["serde"]),
+        ),
+        (
+            "serde".to_owned(),
+            DependencySpec::with_features("1.0.171", &["derive"]),
+        ),
+        ("serde_json".to_owned(), DependencySpec::version("1.0.103")),
+        (
+            "serde_path_to_error".to_owned(),
+            DependencySpec::version("0.1.16"),
+        ),
+        (
+            "serde_with".to_owned(),
+            DependencySpec::with_features("3.0.0", &["chrono"]),
+        ),
+        ("thiserror".to_owned(), DependencySpec::version("1.0.43")),
+    ])
+}
+
+pub fn generate_cargo(
+    config: &Config,
+    model_features: Option<&ModelFeatures>,
+    output: &mut Output,
+) -> Result<()> {
+    if !config.emit_cargo {
+        return Ok(());
+    }
+
+    let package = &config.package;
+    let mut buffer = String::new();
+
+    writeln!(buffer, "{}", CARGO_HEADER)?;
     writeln!(buffer, r#"[package]"#)?;
-    writeln!(buffer, r#"name = "oscal_lib""#)?;
-    writeln!(buffer, r#"version = "0.1.1""#)?;
-    writeln!(buffer, r#"edition = "2021""#)?;
-    writeln!(
-        buffer,
-        r#"authors = ["David Skyberg <davidskyberg@gmail.com"]"#
-    )?;
-    writeln!(buffer, r#"description = "OSCAL lib in Rust""#)?;
-    writeln!(buffer, r#"keywords = ["rust", "oscal"]"#)?;
-    writeln!(
-        buffer,
-        r#"repository = "https://github.com/dskyberg/oscal""#
-    )?;
-    writeln!(buffer, r#"license = "MIT OR Apache 2.0""#)?;
+    writeln!(buffer, r#"name = {}"#, toml_string(&package.name))?;
+    writeln!(buffer, r#"version = {}"#, toml_string(&package.version))?;
+    writeln!(buffer, r#"edition = {}"#, toml_string(&package.edition))?;
+    if !package.authors.is_empty() {
+        writeln!(buffer, r#"authors = {}"#, toml_array(&package.authors))?;
+    }
+    if let Some(description) = &package.description {
+        writeln!(buffer, r#"description = {}"#, toml_string(description))?;
+    }
+    if !package.keywords.is_empty() {
+        writeln!(buffer, r#"keywords = {}"#, toml_array(&package.keywords))?;
+    }
+    if let Some(repository) = &package.repository {
+        writeln!(buffer, r#"repository = {}"#, toml_string(repository))?;
+    }
+    if let Some(license) = &package.license {
+        writeln!(buffer, r#"license = {}"#, toml_string(license))?;
+    }
     writeln!(buffer)?;
+
+    let mut dependencies = IndexMap::from([("oscal_types".to_owned(), config.oscal_types.clone())]);
+    dependencies.extend(default_dependencies());
+    // Configured dependencies replace the defaults with the same name
+    dependencies.extend(config.dependencies.clone());
+
     writeln!(buffer, r#"[dependencies]"#)?;
-    writeln!(
-        buffer,
-        r#"oscal_types = {{ git = "https://github.com/swanky-oscal/oscal_types.git" }}"#
-    )?;
-    writeln!(
-        buffer,
-        r#"chrono = {{ version = "0.4", features = ["serde", "alloc", "now", "serde"] }}"#
-    )?;
-    writeln!(
-        buffer,
-        r#"semver = {{ version = "1.0.18", features = ["serde"] }}"#
-    )?;
-    writeln!(
-        buffer,
-        r#"serde = {{ version = "1.0.171", features = ["derive"] }}"#
-    )?;
-    writeln!(buffer, r#"serde_json = "1.0.103""#)?;
-    writeln!(
-        buffer,
-        r#"serde_with = {{ version = "3.0.0", features = ["chrono"] }}"#
-    )?;
-    writeln!(buffer, r#"thiserror = "1.0.43""#)?;
+    for (name, spec) in &dependencies {
+        writeln!(buffer, "{} = {}", toml_key(name), spec.to_toml())?;
+    }
+
+    let mut features = model_features
+        .map(|features| features.cargo_features())
+        .unwrap_or_default();
+    // Configured features replace the generated ones with the same name
+    features.extend(config.features.clone());
+
+    if !features.is_empty() {
+        writeln!(buffer)?;
+        writeln!(buffer, r#"[features]"#)?;
+        for (name, features) in &features {
+            writeln!(buffer, "{} = {}", toml_key(name), toml_array(features))?;
+        }
+    }
 
+    output.add(Path::new("Cargo.toml"), buffer);
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DependencyDetail;
+
+    /// The generated `Cargo.toml`, if any
+    fn cargo(config: &Config) -> Option<String> {
+        let mut output = Output::new();
+        generate_cargo(config, None, &mut output).expect("oops");
+        output
+            .iter()
+            .find(|(path, _)| path.as_path() == Path::new("Cargo.toml"))
+            .map(|(_, content)| content.to_owned())
+    }
+
+    /// The lines of `cargo` from the `[section]` header to the next blank line
+    fn section<'c>(cargo: &'c str, section: &str) -> Vec<&'c str> {
+        cargo
+            .lines()
+            .skip_while(|line| *line != format!("[{}]", section))
+            .skip(1)
+            .take_while(|line| !line.is_empty())
+            .collect()
+    }
+
+    #[test]
+    fn test_default_cargo() {
+        let cargo = cargo(&Config::default()).expect("oops");
+        // Output::write only overwrites or prunes a Cargo.toml with the header
+        assert!(cargo.starts_with(&format!("{}\n[package]\n", CARGO_HEADER)));
+        assert!(cargo.parse::<toml::Table>().is_ok());
+        assert_eq!(
+            section(&cargo, "package")[..3],
+            [
+                r#"name = "oscal_lib""#,
+                r#"version = "0.1.1""#,
+                r#"edition = "2021""#
+            ]
+        );
+        assert_eq!(
+            section(&cargo, "dependencies")[0],
+            r#"oscal_types = { git = "https://github.com/swanky-oscal/oscal_types.git" }"#
+        );
+        assert!(!cargo.contains("[features]"));
+    }
+
+    #[test]
+    fn test_dependency_sources() {
+        let detail = |detail: DependencyDetail| DependencySpec::Detailed(detail);
+        let config = Config {
+            oscal_types: detail(DependencyDetail {
+                path: Some("../oscal_types".to_owned()),
+                ..Default::default()
+            }),
+            dependencies: IndexMap::from([
+                ("uuid".to_owned(), DependencySpec::version("1")),
+                (
+                    "my_oscal".to_owned(),
+                    detail(DependencyDetail {
+                        git: Some("https://example.com/my_oscal.git".to_owned()),
+                        branch: Some("main".to_owned()),
+                        ..Default::default()
+                    }),
+                ),
+                (
+                    "internal".to_owned(),
+                    detail(DependencyDetail {
+                        version: Some("0.3".to_owned()),
+                        registry: Some("corp".to_owned()),
+                        ..Default::default()
+                    }),
+                ),
+                // Replaces the default, in place
+                ("serde_json".to_owned(), DependencySpec::version("1.0.120")),
+            ]),
+            ..Default::default()
+        };
+        let cargo = cargo(&config).expect("oops");
+        assert!(cargo.parse::<toml::Table>().is_ok());
+        assert_eq!(
+            section(&cargo, "dependencies"),
+            [
+                r#"oscal_types = { path = "../oscal_types" }"#,
+                r#"chrono = { version = "0.4", features = ["serde", "alloc", "now"] }"#,
+                r#"semver = { version = "1.0.18", features = ["serde"] }"#,
+                r#"serde = { version = "1.0.171", features = ["derive"] }"#,
+                r#"serde_json = "1.0.120""#,
+                r#"serde_path_to_error = "0.1.16""#,
+                r#"serde_with = { version = "3.0.0", features = ["chrono"] }"#,
+                r#"thiserror = "1.0.43""#,
+                r#"uuid = "1""#,
+                r#"my_oscal = { git = "https://example.com/my_oscal.git", branch = "main" }"#,
+                r#"internal = { version = "0.3", registry = "corp" }"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_edition_and_features() {
+        let mut config = Config::default();
+        config.package.edition = "2018".to_owned();
+        config.package.authors = vec![];
+        config.features = IndexMap::from([("extra".to_owned(), vec!["dep:uuid".to_owned()])]);
+        let cargo = cargo(&config).expect("oops");
+        assert!(cargo.parse::<toml::Table>().is_ok());
+        assert!(section(&cargo, "package").contains(&r#"edition = "2018""#));
+        assert!(!cargo.contains("authors"));
+        assert_eq!(section(&cargo, "features"), [r#"extra = ["dep:uuid"]"#]);
+    }
+
+    #[test]
+    fn test_no_cargo() {
+        let config = Config {
+            emit_cargo: false,
+            ..Default::default()
+        };
+        assert!(cargo(&config).is_none());
+    }
+}
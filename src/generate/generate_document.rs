@@ -0,0 +1,373 @@
+use anyhow::Result;
+use convert_case::{Case, Casing};
+use std::fmt::Write;
+
+use crate::{Error, Identifiers, Resolver, Schema, SchemaType, TypeRef};
+
+use super::ModelFeatures;
+
+/// Validating datatypes, with a value each rejects.  Newtypes of them
+/// deserialize through the datatype's `TryFrom`, which validates.
+static MALFORMED_VALUES: [(&str, &str); 5] = [
+    ("UUIDDatatype", "not-a-uuid"),
+    ("DateDatatype", "not-a-date"),
+    ("DateTimeDatatype", "not-a-date"),
+    ("DateWithTimezoneDatatype", "not-a-date"),
+    ("DateTimeWithTimezoneDatatype", "not-a-date"),
+];
+
+/// A root OSCAL document model, such as a catalog
+#[derive(Debug, Clone, PartialEq)]
+pub struct DocumentModel {
+    /// The `OscalDocumentType` variant
+    pub variant: String,
+    /// The JSON property name of the document, such as `catalog`
+    pub key: String,
+    pub schema_type: SchemaType,
+    /// A property of the model with a newtype of a validating datatype, and
+    /// a value it rejects.  A generated test parses a document with it.
+    pub malformed: Option<(String, &'static str)>,
+}
+
+impl DocumentModel {
+    /// The Rust path of the model type
+    fn rust_path(&self) -> String {
+        let SchemaType { ns, name, external } = &self.schema_type;
+        match (*external, ns.is_empty()) {
+            (true, true) => name.to_owned(),
+            (true, false) => format!("{}::{}", ns, name),
+            (false, _) => format!("crate::{}", self.schema_type.path()),
+        }
+    }
+}
+
+/// The document models found in the root of the schema
+pub fn document_models(
+    tree: &Schema,
+    resolver: &Resolver,
+    idents: &Identifiers,
+) -> Result<Vec<DocumentModel>> {
+    let mut result = vec![];
+    for (key, type_ref) in &tree.documents {
+        let Some(schema_type) = resolver.resolve(type_ref) else {
+            return Err(Error::ResolverFailure.into());
+        };
+        result.push(DocumentModel {
+            variant: idents.type_name(&key.to_case(Case::Pascal)),
+            key: key.to_owned(),
+            malformed: malformed_prop(&schema_type, tree, resolver),
+            schema_type,
+        });
+    }
+    Ok(result)
+}
+
+/// The first single valued property of the model that is a newtype of a
+/// validating datatype, with a value it rejects.  Properties typed with the
+/// datatype itself are skipped, since they deserialize with the datatype's
+/// own serde impls, which aren't known to validate.
+fn malformed_prop(
+    schema_type: &SchemaType,
+    tree: &Schema,
+    resolver: &Resolver,
+) -> Option<(String, &'static str)> {
+    let object = |schema_type: &SchemaType| {
+        tree.objects()
+            .into_iter()
+            .find(|obj| SchemaType::from(*obj) == *schema_type)
+    };
+    let malformed = |schema_type: &SchemaType| {
+        MALFORMED_VALUES
+            .iter()
+            .find(|(name, _)| schema_type.ns == "oscal_types" && schema_type.name == *name)
+            .map(|(_, value)| *value)
+    };
+    let props = object(schema_type)?.props.as_ref()?;
+    props
+        .values()
+        .filter(|prop| !prop.array && prop.enums.is_none())
+        .find_map(|prop| {
+            let newtype = object(&resolver.resolve(&prop.type_ref)?)?;
+            let value =
+                malformed(&resolver.resolve(&TypeRef::from_ref(newtype.type_ref.clone()))?)?;
+            Some((prop.json_name.clone(), value))
+        })
+}
+
+pub fn generate_oscal_document(
+    buffer: &mut String,
+    models: &[DocumentModel],
+    features: Option<&ModelFeatures>,
+) -> Result<()> {
+    // Without root documents, such as for a schema of shared definitions,
+    // there is nothing to dispatch on.
+    if models.is_empty() {
+        return Ok(());
+    }
+
+    writeln!(
+        buffer,
+        r##"
+
+use serde::{{Deserialize, Serialize}};
+use serde_with::skip_serializing_none;
+"##
+    )?;
+    writeln!(
+        buffer,
+        r##"#[skip_serializing_none]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub enum OscalDocumentType {{"##
+    )?;
+    for model in models {
+        if let (Some(features), false) = (features, model.schema_type.external) {
+            writeln!(buffer, "    {}", features.cfg(&model.schema_type.ns))?;
+        }
+        writeln!(buffer, r#"    #[serde(rename = "{}")]"#, &model.key)?;
+        writeln!(
+            buffer,
+            "    {}(Box<{}>),",
+            &model.variant,
+            model.rust_path()
+        )?;
+    }
+    writeln!(buffer, "}}")?;
+
+    writeln!(buffer)?;
+
+    writeln!(
+        buffer,
+        r##"#[skip_serializing_none]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct OscalDocument {{
+    #[serde(rename = "$schema")]
+    pub schema: Option<String>,
+    #[serde(flatten)]
+    pub document: OscalDocumentType
+}}
+
+impl OscalDocument {{
+    /// Parse a document from JSON.  Errors name the path to the offending
+    /// value, such as `catalog.metadata.last-modified`.
+    pub fn from_json_str(json: &str) -> Result<Self, crate::error::Error> {{
+        let error = |path: String, message: String| crate::error::Error::Deserialize {{ path, message }};
+        // serde buffers flattened content, which loses the path.  So the
+        // document is parsed on its own, without `$schema`.
+        let mut document: serde_json::Map<String, serde_json::Value> =
+            serde_json::from_str(json).map_err(|e| error(".".to_owned(), e.to_string()))?;
+        let schema = match document.remove("$schema") {{
+            None | Some(serde_json::Value::Null) => None,
+            Some(serde_json::Value::String(schema)) => Some(schema),
+            Some(_) => return Err(error("$schema".to_owned(), "expected a string".to_owned())),
+        }};
+        let document = serde_path_to_error::deserialize(serde_json::Value::Object(document))
+            .map_err(|e| error(e.path().to_string(), e.inner().to_string()))?;
+        Ok(Self {{ schema, document }})
+    }}
+}}
+    "##
+    )?;
+    generate_document_tests(buffer, models, features)?;
+    Ok(())
+}
+
+/// Tests that a malformed value in each model fails when the document is
+/// parsed, naming the path to it
+fn generate_document_tests(
+    buffer: &mut String,
+    models: &[DocumentModel],
+    features: Option<&ModelFeatures>,
+) -> Result<()> {
+    let models = models
+        .iter()
+        .filter_map(|model| Some((model, model.malformed.as_ref()?)))
+        .collect::<Vec<_>>();
+    if models.is_empty() {
+        return Ok(());
+    }
+
+    writeln!(
+        buffer,
+        "#[cfg(test)]\nmod document_tests {{\n    use super::OscalDocument;"
+    )?;
+    for (model, (json_name, value)) in models {
+        writeln!(buffer)?;
+        if let (Some(features), false) = (features, model.schema_type.external) {
+            writeln!(buffer, "    {}", features.cfg(&model.schema_type.ns))?;
+        }
+        writeln!(
+            buffer,
+            r##"    #[test]
+    fn test_malformed_{test}() {{
+        let json = serde_json::json!({{ {key:?}: {{ {json_name:?}: {value:?} }} }});
+        match OscalDocument::from_json_str(&json.to_string()) {{
+            Err(crate::error::Error::Deserialize {{ path, .. }}) => {{
+                assert_eq!(path, {path:?})
+            }}
+            other => panic!("{{:?}}", other),
+        }}
+    }}"##,
+            test = model.variant.to_case(Case::Snake),
+            key = model.key,
+            path = format!("{}.{}", model.key, json_name),
+        )?;
+    }
+    writeln!(buffer, "}}")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Property, SchemaObject};
+
+    #[test]
+    fn test_document_models() {
+        let mut resolver = Resolver::new();
+        resolver.add_type(
+            "#assembly_oscal-catalog_catalog",
+            SchemaType::new("oscal_catalog", "Catalog"),
+        );
+        resolver.add_type(
+            "#assembly_oscal-ssp_system-security-plan",
+            SchemaType::new("oscal_ssp", "SystemSecurityPlan"),
+        );
+
+        let mut tree = Schema::new("", "1.1.2", "");
+        for (key, _ref) in [
+            ("catalog", "#assembly_oscal-catalog_catalog"),
+            (
+                "system-security-plan",
+                "#assembly_oscal-ssp_system-security-plan",
+            ),
+        ] {
+            tree.documents
+                .insert(key.to_owned(), TypeRef::from_ref(Some(_ref.to_owned())));
+        }
+
+        let models = document_models(&tree, &resolver, &Identifiers::default()).expect("oops");
+        let models: Vec<(&str, &str, String)> = models
+            .iter()
+            .map(|model| {
+                (
+                    model.variant.as_str(),
+                    model.key.as_str(),
+                    model.rust_path(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            models,
+            vec![
+                (
+                    "Catalog",
+                    "catalog",
+                    "crate::oscal_catalog::Catalog".to_owned()
+                ),
+                (
+                    "SystemSecurityPlan",
+                    "system-security-plan",
+                    "crate::oscal_ssp::SystemSecurityPlan".to_owned()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_malformed_document_tests() {
+        let mut resolver = Resolver::new();
+        resolver.add_type("#catalog", SchemaType::new("oscal_catalog", "Catalog"));
+        resolver.add_type("#profile", SchemaType::new("oscal_profile", "Profile"));
+        resolver.add_type("#mapping", SchemaType::new("oscal_mapping", "Mapping"));
+        resolver.add_type("#profile-id", SchemaType::new("profile_id", "ProfileId"));
+        resolver.add_type(
+            "#/definitions/UUIDDatatype",
+            SchemaType::new("oscal_types", "UUIDDatatype"),
+        );
+        resolver.add_type(
+            "#/definitions/StringDatatype",
+            SchemaType::new("oscal_types", "StringDatatype"),
+        );
+
+        let prop = |json_name: &str, _ref: &str| {
+            let prop = Property {
+                optional: false,
+                ..Property::test_property(&json_name.replace('-', "_"), json_name, _ref)
+            };
+            (prop.name.clone(), prop)
+        };
+        let object = |ns: &str, name: &str, props: Vec<(String, Property)>| SchemaObject {
+            props: Some(props.into_iter().collect()),
+            ..SchemaObject::test_object(ns, name)
+        };
+        let mut tree = Schema::new("", "1.1.2", "");
+        for obj in [
+            // The datatype itself, which may not validate when deserialized
+            object(
+                "oscal_catalog",
+                "Catalog",
+                vec![
+                    prop("title", "#/definitions/StringDatatype"),
+                    prop("uuid", "#/definitions/UUIDDatatype"),
+                ],
+            ),
+            // Through a newtype, which validates with TryFrom
+            object(
+                "oscal_profile",
+                "Profile",
+                vec![prop("profile-id", "#profile-id")],
+            ),
+            SchemaObject {
+                type_ref: Some("#/definitions/UUIDDatatype".to_owned()),
+                ..SchemaObject::test_object("profile_id", "ProfileId")
+            },
+            // Nothing validating
+            object(
+                "oscal_mapping",
+                "Mapping",
+                vec![prop("title", "#/definitions/StringDatatype")],
+            ),
+        ] {
+            tree.add_object(&obj.ns.clone(), &obj).expect("oops");
+        }
+        for key in ["catalog", "profile", "mapping"] {
+            tree.documents
+                .insert(key.to_owned(), TypeRef::from_ref(Some(format!("#{}", key))));
+        }
+
+        let models = document_models(&tree, &resolver, &Identifiers::default()).expect("oops");
+        let malformed: Vec<Option<(String, &str)>> =
+            models.iter().map(|model| model.malformed.clone()).collect();
+        assert_eq!(
+            malformed,
+            vec![None, Some(("profile-id".to_owned(), "not-a-uuid")), None]
+        );
+
+        let mut buffer = String::new();
+        generate_oscal_document(&mut buffer, &models, None).expect("oops");
+        // The document is parsed without flatten, which would lose the path
+        assert!(buffer.contains(
+            "let document = serde_path_to_error::deserialize(serde_json::Value::Object(document))"
+        ));
+        let tests = &buffer[buffer.find("#[cfg(test)]").expect("oops")..];
+        assert_eq!(
+            tests,
+            r##"#[cfg(test)]
+mod document_tests {
+    use super::OscalDocument;
+
+    #[test]
+    fn test_malformed_profile() {
+        let json = serde_json::json!({ "profile": { "profile-id": "not-a-uuid" } });
+        match OscalDocument::from_json_str(&json.to_string()) {
+            Err(crate::error::Error::Deserialize { path, .. }) => {
+                assert_eq!(path, "profile.profile-id")
+            }
+            other => panic!("{:?}", other),
+        }
+    }
+}
+"##
+        );
+    }
+}
@@ -1,16 +1,29 @@
 use anyhow::Result;
-use std::fs::File;
-use std::io::prelude::*;
-use std::path::PathBuf;
+use std::fmt::Write;
+use std::path::Path;
 
-pub fn generate_mod(path: &PathBuf, mods: &[&str]) -> Result<()> {
-    let mut file_path = path.to_owned();
-    file_path.push("mod.rs");
-    let mut buffer = File::create(file_path)?;
+use super::{code, DocComment, Output};
+
+pub fn generate_mod(path: &Path, mods: &[&str], output: &mut Output) -> Result<()> {
+    let file_path = path.join("mod.rs");
+    let mut buffer = String::new();
+
+    let module = path
+        .iter()
+        .skip(1)
+        .map(|part| part.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("::");
+    if !module.is_empty() {
+        let mut doc = DocComment::new();
+        doc.markdown(&format!("The {} module of the OSCAL model", code(&module)));
+        writeln!(buffer, "{}", doc.render("//!"))?;
+    }
 
     for mod_name in mods.iter() {
         writeln!(buffer, "pub mod {};", mod_name)?;
     }
 
+    output.add(&file_path, buffer);
     Ok(())
 }
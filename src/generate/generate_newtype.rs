@@ -0,0 +1,543 @@
+//! The API shared by every generated newtype, such as `pub struct ControlId(TokenDatatype);`
+//!
+//! Every newtype gets `into_inner`, `From<Self>` for the inner type, `Deref`,
+//! `AsRef` and `Borrow`.  Newtypes that can be represented as text also get
+//! `FromStr`, `Display`, `TryFrom<&str>` and `TryFrom<String>`.  Newtypes of
+//! another newtype, or of a type outside of the crate, are built from their
+//! inner type with `From`, and so `TryFrom`.  Those that aren't text, such as
+//! one bound to a struct by `[types]`, only get that.
+//!
+//! Newtypes over validating `oscal_types` datatypes deserialize through
+//! their `TryFrom`, so malformed values are rejected when a document is
+//! parsed, rather than later.  They serialize as the same type, `String` or
+//! a primitive such as `u64`, so they round trip whatever the datatype's
+//! own serde impls do.
+use anyhow::Result;
+use std::fmt::Write;
+
+/// How the inner type of a newtype is built and viewed
+#[derive(Debug, Clone, PartialEq)]
+pub enum NewtypeKind {
+    /// The inner type is `String`
+    String,
+    /// The inner type is an `oscal_types` datatype, which derefs to
+    /// `ref_type`, and is built with a validating `TryFrom<&ref_type>`
+    Datatype { ref_type: String },
+    /// The inner type is another newtype, or a type outside of the crate.
+    /// If `text` is set, it implements `FromStr` and `Display`.
+    Opaque { text: bool },
+}
+
+impl NewtypeKind {
+    /// The type `Deref`, `AsRef` and `Borrow` give access to
+    fn target<'a>(&'a self, inner: &'a str) -> &'a str {
+        match self {
+            Self::String => "str",
+            Self::Datatype { ref_type } => ref_type,
+            Self::Opaque { .. } => inner,
+        }
+    }
+
+    /// Whether the newtype gets the text conversions
+    fn is_text(&self) -> bool {
+        match self {
+            Self::String | Self::Datatype { .. } => true,
+            Self::Opaque { text } => *text,
+        }
+    }
+}
+
+pub fn generate_newtype(
+    buffer: &mut String,
+    name: &str,
+    inner: &str,
+    kind: &NewtypeKind,
+) -> Result<()> {
+    let target = kind.target(inner);
+
+    // Deserialize through the validating TryFrom, so that bad values fail
+    // when the document is parsed
+    match kind {
+        NewtypeKind::Datatype { ref_type } if ref_type == "str" => {
+            writeln!(buffer, r#"#[serde(try_from = "String", into = "String")]"#)?
+        }
+        NewtypeKind::Datatype { ref_type } => writeln!(
+            buffer,
+            r#"#[serde(try_from = "{}", into = "{}")]"#,
+            ref_type, ref_type
+        )?,
+        _ => {}
+    }
+    writeln!(buffer, "pub struct {}({});", name, inner)?;
+    writeln!(
+        buffer,
+        r##"
+impl {name} {{
+    /// Consume the newtype, returning the inner value
+    pub fn into_inner(self) -> {inner} {{
+        self.0
+    }}
+}}
+
+impl From<{name}> for {inner} {{
+    fn from(value: {name}) -> Self {{
+        value.0
+    }}
+}}
+
+impl std::ops::Deref for {name} {{
+    type Target = {target};
+    fn deref(&self) -> &Self::Target {{
+        &self.0
+    }}
+}}
+
+impl AsRef<{target}> for {name} {{
+    fn as_ref(&self) -> &{target} {{
+        &self.0
+    }}
+}}
+
+impl std::borrow::Borrow<{target}> for {name} {{
+    fn borrow(&self) -> &{target} {{
+        &self.0
+    }}
+}}"##
+    )?;
+
+    // Nothing to validate, the inner value is already typed
+    if let NewtypeKind::Opaque { .. } = kind {
+        writeln!(
+            buffer,
+            r##"
+impl From<{inner}> for {name} {{
+    fn from(value: {inner}) -> Self {{
+        Self(value)
+    }}
+}}"##
+        )?;
+    }
+
+    if !kind.is_text() {
+        return Ok(());
+    }
+
+    // Display, via whatever the newtype derefs to
+    writeln!(
+        buffer,
+        r##"
+impl std::fmt::Display for {name} {{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {{
+        std::fmt::Display::fmt(&**self, f)
+    }}
+}}"##
+    )?;
+
+    match kind {
+        NewtypeKind::String => writeln!(
+            buffer,
+            r##"
+impl TryFrom<&str> for {name} {{
+    type Error = Error;
+    fn try_from(value: &str) -> Result<Self, Self::Error> {{
+        Ok(Self(value.to_string()))
+    }}
+}}
+
+impl TryFrom<String> for {name} {{
+    type Error = Error;
+    fn try_from(value: String) -> Result<Self, Self::Error> {{
+        Ok(Self(value))
+    }}
+}}
+
+impl std::str::FromStr for {name} {{
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {{
+        Self::try_from(s)
+    }}
+}}"##
+        )?,
+        NewtypeKind::Datatype { ref_type } if ref_type == "str" => writeln!(
+            buffer,
+            r##"
+impl TryFrom<&str> for {name} {{
+    type Error = Error;
+    fn try_from(value: &str) -> Result<Self, Self::Error> {{
+        Ok(Self({inner}::try_from(value)?))
+    }}
+}}
+
+impl TryFrom<String> for {name} {{
+    type Error = Error;
+    fn try_from(value: String) -> Result<Self, Self::Error> {{
+        Self::try_from(value.as_str())
+    }}
+}}
+
+impl From<{name}> for String {{
+    fn from(value: {name}) -> Self {{
+        value.to_string()
+    }}
+}}
+
+impl std::str::FromStr for {name} {{
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {{
+        Self::try_from(s)
+    }}
+}}"##
+        )?,
+        NewtypeKind::Datatype { ref_type } => writeln!(
+            buffer,
+            r##"
+impl TryFrom<&{ref_type}> for {name} {{
+    type Error = Error;
+    fn try_from(value: &{ref_type}) -> Result<Self, Self::Error> {{
+        Ok(Self({inner}::try_from(value)?))
+    }}
+}}
+
+impl TryFrom<{ref_type}> for {name} {{
+    type Error = Error;
+    fn try_from(value: {ref_type}) -> Result<Self, Self::Error> {{
+        Self::try_from(&value)
+    }}
+}}
+
+impl TryFrom<&str> for {name} {{
+    type Error = Error;
+    fn try_from(value: &str) -> Result<Self, Self::Error> {{
+        let value = value
+            .parse::<{ref_type}>()
+            .map_err(|e| Error::Parse(e.to_string()))?;
+        Self::try_from(&value)
+    }}
+}}
+
+impl TryFrom<String> for {name} {{
+    type Error = Error;
+    fn try_from(value: String) -> Result<Self, Self::Error> {{
+        Self::try_from(value.as_str())
+    }}
+}}
+
+impl From<{name}> for {ref_type} {{
+    fn from(value: {name}) -> Self {{
+        *value
+    }}
+}}
+
+impl std::str::FromStr for {name} {{
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {{
+        Self::try_from(s)
+    }}
+}}"##
+        )?,
+        NewtypeKind::Opaque { .. } => writeln!(
+            buffer,
+            r##"
+impl TryFrom<&str> for {name} {{
+    type Error = Error;
+    fn try_from(value: &str) -> Result<Self, Self::Error> {{
+        value
+            .parse::<{inner}>()
+            .map(Self)
+            .map_err(|e| Error::Parse(e.to_string()))
+    }}
+}}
+
+impl TryFrom<String> for {name} {{
+    type Error = Error;
+    fn try_from(value: String) -> Result<Self, Self::Error> {{
+        Self::try_from(value.as_str())
+    }}
+}}
+
+impl std::str::FromStr for {name} {{
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {{
+        Self::try_from(s)
+    }}
+}}"##
+        )?,
+    }
+
+    if let NewtypeKind::Datatype { ref_type } = kind {
+        generate_round_trip_test(buffer, name, ref_type)?;
+    }
+    Ok(())
+}
+
+/// A value of the primitive `ref_type` that every datatype over it accepts,
+/// as JSON
+fn sample_value(ref_type: &str) -> Option<&'static str> {
+    match ref_type {
+        "bool" => Some("true"),
+        "u8" | "u16" | "u32" | "u64" | "i8" | "i16" | "i32" | "i64" => Some("1"),
+        _ => None,
+    }
+}
+
+/// A test that a newtype over a primitive serializes as the primitive it
+/// deserializes from
+fn generate_round_trip_test(buffer: &mut String, name: &str, ref_type: &str) -> Result<()> {
+    let Some(value) = sample_value(ref_type) else {
+        return Ok(());
+    };
+    writeln!(
+        buffer,
+        r##"
+#[cfg(test)]
+mod round_trip {{
+    #[test]
+    fn test_round_trip() {{
+        let value: super::{name} = serde_json::from_str({value:?}).expect("oops");
+        let json = serde_json::to_string(&value).expect("oops");
+        assert_eq!(json, {value:?});
+        assert_eq!(serde_json::from_str::<super::{name}>(&json).expect("oops"), value);
+    }}
+}}"##
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn newtype(name: &str, inner: &str, kind: &NewtypeKind) -> String {
+        let mut buffer = String::new();
+        generate_newtype(&mut buffer, name, inner, kind).expect("oops");
+        buffer
+    }
+
+    /// The head of every item in `code`, such as `impl AsRef<str> for Remarks`
+    fn items(code: &str) -> Vec<&str> {
+        code.lines()
+            .filter(|line| line.starts_with("impl") || line.starts_with("pub struct"))
+            .map(|line| line.trim_end_matches(" {"))
+            .collect()
+    }
+
+    /// The body of the item starting with `head`
+    fn body<'c>(code: &'c str, head: &str) -> &'c str {
+        let start = code.find(&format!("{} {{", head)).expect("oops");
+        let end = code[start..].find("\n}").expect("oops");
+        &code[start..start + end + 2]
+    }
+
+    #[test]
+    fn test_string_newtype() {
+        let code = newtype("Remarks", "String", &NewtypeKind::String);
+        assert_eq!(
+            items(&code),
+            vec![
+                "pub struct Remarks(String);",
+                "impl Remarks",
+                "impl From<Remarks> for String",
+                "impl std::ops::Deref for Remarks",
+                "impl AsRef<str> for Remarks",
+                "impl std::borrow::Borrow<str> for Remarks",
+                "impl std::fmt::Display for Remarks",
+                "impl TryFrom<&str> for Remarks",
+                "impl TryFrom<String> for Remarks",
+                "impl std::str::FromStr for Remarks",
+            ]
+        );
+        assert_eq!(
+            body(&code, "impl TryFrom<String> for Remarks"),
+            "impl TryFrom<String> for Remarks {
+    type Error = Error;
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Ok(Self(value))
+    }
+}"
+        );
+    }
+
+    #[test]
+    fn test_datatype_newtype() {
+        let kind = NewtypeKind::Datatype {
+            ref_type: "u64".to_owned(),
+        };
+        let code = newtype("Count", "NonNegativeIntegerDatatype", &kind);
+        assert_eq!(
+            items(&code),
+            vec![
+                "pub struct Count(NonNegativeIntegerDatatype);",
+                "impl Count",
+                "impl From<Count> for NonNegativeIntegerDatatype",
+                "impl std::ops::Deref for Count",
+                "impl AsRef<u64> for Count",
+                "impl std::borrow::Borrow<u64> for Count",
+                "impl std::fmt::Display for Count",
+                "impl TryFrom<&u64> for Count",
+                "impl TryFrom<u64> for Count",
+                "impl TryFrom<&str> for Count",
+                "impl TryFrom<String> for Count",
+                "impl From<Count> for u64",
+                "impl std::str::FromStr for Count",
+            ]
+        );
+        // Text is parsed, then validated
+        assert_eq!(
+            body(&code, "impl TryFrom<&str> for Count"),
+            "impl TryFrom<&str> for Count {
+    type Error = Error;
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let value = value
+            .parse::<u64>()
+            .map_err(|e| Error::Parse(e.to_string()))?;
+        Self::try_from(&value)
+    }
+}"
+        );
+        assert_eq!(
+            body(&code, "impl TryFrom<&u64> for Count"),
+            "impl TryFrom<&u64> for Count {
+    type Error = Error;
+    fn try_from(value: &u64) -> Result<Self, Self::Error> {
+        Ok(Self(NonNegativeIntegerDatatype::try_from(value)?))
+    }
+}"
+        );
+    }
+
+    #[test]
+    fn test_datatype_deserializes_through_try_from() {
+        let kind = NewtypeKind::Datatype {
+            ref_type: "str".to_owned(),
+        };
+        let code = newtype("ControlId", "TokenDatatype", &kind);
+        assert!(code.starts_with(
+            "#[serde(try_from = \"String\", into = \"String\")]\npub struct ControlId(TokenDatatype);"
+        ));
+        assert_eq!(
+            items(&code),
+            vec![
+                "pub struct ControlId(TokenDatatype);",
+                "impl ControlId",
+                "impl From<ControlId> for TokenDatatype",
+                "impl std::ops::Deref for ControlId",
+                "impl AsRef<str> for ControlId",
+                "impl std::borrow::Borrow<str> for ControlId",
+                "impl std::fmt::Display for ControlId",
+                "impl TryFrom<&str> for ControlId",
+                "impl TryFrom<String> for ControlId",
+                "impl From<ControlId> for String",
+                "impl std::str::FromStr for ControlId",
+            ]
+        );
+
+        let kind = NewtypeKind::Datatype {
+            ref_type: "u64".to_owned(),
+        };
+        let code = newtype("Count", "NonNegativeIntegerDatatype", &kind);
+        assert!(code.starts_with("#[serde(try_from = \"u64\", into = \"u64\")]\npub struct Count("));
+
+        // Nothing to validate
+        for kind in [NewtypeKind::String, NewtypeKind::Opaque { text: true }] {
+            assert!(!newtype("Remarks", "String", &kind).contains("#[serde"));
+        }
+    }
+
+    #[test]
+    fn test_datatype_round_trip() {
+        let kind = NewtypeKind::Datatype {
+            ref_type: "u64".to_owned(),
+        };
+        let code = newtype("Count", "NonNegativeIntegerDatatype", &kind);
+        assert_eq!(
+            body(&code, "impl From<Count> for u64"),
+            "impl From<Count> for u64 {
+    fn from(value: Count) -> Self {
+        *value
+    }
+}"
+        );
+        assert!(code.ends_with(
+            r#"
+#[cfg(test)]
+mod round_trip {
+    #[test]
+    fn test_round_trip() {
+        let value: super::Count = serde_json::from_str("1").expect("oops");
+        let json = serde_json::to_string(&value).expect("oops");
+        assert_eq!(json, "1");
+        assert_eq!(serde_json::from_str::<super::Count>(&json).expect("oops"), value);
+    }
+}
+"#
+        ));
+
+        let kind = NewtypeKind::Datatype {
+            ref_type: "bool".to_owned(),
+        };
+        let code = newtype("Flag", "BooleanDatatype", &kind);
+        assert!(code.contains(r#"serde_json::from_str("true")"#));
+
+        // Text round trips as String, without a sample value to test with
+        let kind = NewtypeKind::Datatype {
+            ref_type: "str".to_owned(),
+        };
+        assert!(!newtype("ControlId", "TokenDatatype", &kind).contains("mod round_trip"));
+    }
+
+    #[test]
+    fn test_opaque_newtype() {
+        let code = newtype("PartId", "ControlId", &NewtypeKind::Opaque { text: true });
+        assert_eq!(
+            items(&code),
+            vec![
+                "pub struct PartId(ControlId);",
+                "impl PartId",
+                "impl From<PartId> for ControlId",
+                "impl std::ops::Deref for PartId",
+                "impl AsRef<ControlId> for PartId",
+                "impl std::borrow::Borrow<ControlId> for PartId",
+                "impl From<ControlId> for PartId",
+                "impl std::fmt::Display for PartId",
+                "impl TryFrom<&str> for PartId",
+                "impl TryFrom<String> for PartId",
+                "impl std::str::FromStr for PartId",
+            ]
+        );
+        assert_eq!(
+            body(&code, "impl TryFrom<&str> for PartId"),
+            "impl TryFrom<&str> for PartId {
+    type Error = Error;
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        value
+            .parse::<ControlId>()
+            .map(Self)
+            .map_err(|e| Error::Parse(e.to_string()))
+    }
+}"
+        );
+
+        // Not text, so built from the inner value only
+        let code = newtype("Meta", "Metadata", &NewtypeKind::Opaque { text: false });
+        assert_eq!(
+            items(&code),
+            vec![
+                "pub struct Meta(Metadata);",
+                "impl Meta",
+                "impl From<Meta> for Metadata",
+                "impl std::ops::Deref for Meta",
+                "impl AsRef<Metadata> for Meta",
+                "impl std::borrow::Borrow<Metadata> for Meta",
+                "impl From<Metadata> for Meta",
+            ]
+        );
+        assert_eq!(
+            body(&code, "impl From<Metadata> for Meta"),
+            "impl From<Metadata> for Meta {
+    fn from(value: Metadata) -> Self {
+        Self(value)
+    }
+}"
+        );
+    }
+}
@@ -1,45 +1,80 @@
 use anyhow::Result;
-use indexmap::IndexMap;
 
-use crate::{Error, Namespace, Property, Resolver};
+use crate::{Error, Namespace, SchemaObject, SchemaType};
+
+use super::{code, link, Context, DocComment};
 
 pub(super) fn generate_props(
-    props: &Option<IndexMap<String, Property>>,
-    resolver: &Resolver,
+    obj: &SchemaObject,
+    ctx: &Context,
     name_space: &mut Namespace,
 ) -> Result<Option<String>> {
-    let Some(props) = props else {
+    let Some(props) = &obj.props else {
         return Ok(None);
     };
+    let path = SchemaType::from(obj).path();
 
     let mut result = vec![];
 
-    for (_prop_name, prop) in props {
-        let (fixed_name, name, is_reserved) = prop.name();
-        if is_reserved {
-            result.push(format!(r##"    #[serde(rename = "{}")]"##, &name));
-        }
-        let Some(schema_type) = resolver.resolve(&prop.type_ref) else {
-            eprintln!("Failed to resolve: {} {:?}", &prop.name, &prop.type_ref);
+    for (prop_name, prop) in props {
+        let Some(schema_type) = ctx.resolver.resolve(&prop.type_ref) else {
             return Err(Error::ResolverFailure.into());
         };
 
-        name_space.add_type(&schema_type)?;
+        // The name the type is imported as
+        let local_name = name_space.add_type(&schema_type)?;
 
+        let mut doc = DocComment::new();
         if let Some(title) = &prop.title {
-            result.push(format!("    /// {}", title));
+            doc.text(title);
         }
         if let Some(description) = &prop.description {
-            result.push(format!("    /// {}", description));
+            doc.text(description);
+        }
+        doc.schema("JSON property", &code(&prop.json_name))
+            .schema("Type", &link(&local_name));
+        result.push(doc.render("    ///").trim_end().to_owned());
+        if prop.needs_rename() {
+            // Debug formatting escapes quotes and backslashes in JSON names
+            result.push(format!("    #[serde(rename = {:?})]", &prop.json_name));
+        }
+
+        let mut target_name = local_name;
+        if ctx.graph.is_boxed(&path, prop_name) {
+            target_name = format!("Box<{}>", &target_name);
         }
-        let mut target_name = schema_type.name;
         if prop.array {
             target_name = format!("Vec<{}>", &target_name);
         }
         if prop.optional {
             target_name = format!("Option<{}>", &target_name);
         }
-        result.push(format!("    pub {}: {},", &fixed_name, &target_name));
+        result.push(format!("    pub {}: {},", &prop.name, &target_name));
     }
     Ok(Some(result.join("\n")))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Config, Property, Resolver, Schema};
+    use indexmap::IndexMap;
+
+    #[test]
+    fn test_rename_escapes_json_name() {
+        let json_name = r#"say "hi"\"#;
+        let prop = Property::test_property("say_hi", json_name, "#/definitions/StringDatatype");
+        let obj = SchemaObject {
+            props: Some(IndexMap::from([("say_hi".to_owned(), prop)])),
+            ..SchemaObject::test_object("greeting", "Greeting")
+        };
+        let resolver = Resolver::new();
+        let tree = Schema::new("", "", "");
+        let ctx = Context::new(&tree, &resolver, &Config::default()).expect("oops");
+
+        let props = generate_props(&obj, &ctx, &mut Namespace::new(""))
+            .expect("oops")
+            .expect("oops");
+        assert!(props.contains(r#"    #[serde(rename = "say \"hi\"\\")]"#));
+    }
+}
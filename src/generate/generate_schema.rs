@@ -1,18 +1,20 @@
-use std::fs::create_dir_all;
-use std::path::{Path, PathBuf};
+use std::path::Path;
 
 use anyhow::Result;
 
-use crate::{Resolver, Schema, TreeEntry};
+use crate::{Schema, TreeEntry};
 
-use super::{generate_mod, generate_struct};
+use super::{generate_mod, generate_struct, Context, Output};
 
-pub fn generate_schema(path: &PathBuf, tree: &Schema, resolver: &Resolver) -> Result<()> {
+pub fn generate_schema(
+    path: &Path,
+    tree: &Schema,
+    ctx: &Context,
+    output: &mut Output,
+) -> Result<()> {
     let mut mods: Vec<&str> = vec![];
     let mut mods_done = false;
 
-    create_dir_all(path)?;
-
     // Assemble the mod info
     for (name, _) in tree.iter() {
         mods.push(name);
@@ -23,11 +25,11 @@ pub fn generate_schema(path: &PathBuf, tree: &Schema, resolver: &Resolver) -> Re
             TreeEntry::Object(obj) => {
                 if path.ends_with(name) {
                     // parent::parent should be parent/mod.rs
-                    generate_struct(path, name, true, obj, Some(&mods), resolver)?;
+                    generate_struct(path, name, true, obj, Some(&mods), ctx, output)?;
                     mods_done = true;
                 } else {
                     // parent::child should be parent/child.rs
-                    generate_struct(path, name, false, obj, None, resolver)?;
+                    generate_struct(path, name, false, obj, None, ctx, output)?;
                 }
             }
 
@@ -35,13 +37,13 @@ pub fn generate_schema(path: &PathBuf, tree: &Schema, resolver: &Resolver) -> Re
                 // Reduce Tree(name)/Object(name) to Object(name)
                 if tree.is_reducable(name) {
                     let obj = tree.get_object(name)?;
-                    generate_struct(path, name, false, obj, None, resolver)?;
+                    generate_struct(path, name, false, obj, None, ctx, output)?;
                     continue;
                 }
 
-                let path = Path::new(&path).join(name).to_path_buf();
+                let path = path.join(name);
 
-                generate_schema(&path, tree, resolver)?;
+                generate_schema(&path, tree, ctx, output)?;
             }
         }
     }
@@ -50,5 +52,5 @@ pub fn generate_schema(path: &PathBuf, tree: &Schema, resolver: &Resolver) -> Re
         return Ok(());
     }
 
-    generate_mod(path, &mods)
+    generate_mod(path, &mods, output)
 }
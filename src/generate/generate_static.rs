@@ -2,10 +2,24 @@ use anyhow::Result;
 use std::fs;
 use std::path::Path;
 
-pub fn generate_static(output_path: &Path, static_path: &Path) -> Result<()> {
-    let error_output_path = output_path.join("src/error.rs");
-    let error_static_path = static_path.join("src/error.rs");
-    fs::copy(error_static_path, error_output_path)?;
+use super::Output;
+
+/// Hand written files, copied into the generated crate as is
+static STATIC_FILES: [&str; 2] = ["src/error.rs", "src/introspect.rs"];
+
+pub fn generate_static(static_path: &Path, output: &mut Output) -> Result<()> {
+    for (file, content) in read_static(static_path)? {
+        output.add(Path::new(file), content);
+    }
 
     Ok(())
 }
+
+/// The hand written files, with their content
+pub fn read_static(static_path: &Path) -> Result<Vec<(&'static str, String)>> {
+    let mut result = vec![];
+    for file in STATIC_FILES {
+        result.push((file, fs::read_to_string(static_path.join(file))?));
+    }
+    Ok(result)
+}
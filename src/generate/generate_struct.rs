@@ -1,64 +1,47 @@
 use anyhow::Result;
-use std::fs::File;
-use std::io::prelude::*;
-use std::path::PathBuf;
+use std::fmt::Write;
+use std::path::Path;
 
 use oscal_types::get_ref_type;
 
-use crate::{Error, Namespace, Resolver, SchemaObject, SchemaType, TypeRef};
+use crate::{Collision, Error, Namespace, Resolver, SchemaObject, SchemaType, TypeGraph, TypeRef};
 
-use super::generate_props;
-
-fn split_str_on_words(comment: &str) -> Vec<String> {
-    if comment.len() < 80 {
-        return vec![comment.to_owned()];
-    }
-
-    let mut results = vec![];
-
-    let mut tmp_str = String::new();
-    for word in comment.split_whitespace() {
-        if tmp_str.is_empty() {
-            tmp_str.push_str(word);
-        } else {
-            tmp_str.push_str(&format!(" {}", word));
-        }
-        if tmp_str.len() > 75 {
-            results.push(tmp_str.clone());
-            tmp_str.clear();
-        }
-    }
-    if !tmp_str.is_empty() {
-        results.push(tmp_str.clone());
-    }
-
-    results
-}
+use super::{
+    code, generate_newtype, generate_props, link, Context, DocComment, NewtypeKind, Output,
+};
 
 pub(super) fn generate_struct(
-    path: &PathBuf,
+    path: &Path,
     name: &str,
     is_mod: bool,
     obj: &SchemaObject,
     mods: Option<&Vec<&str>>,
-    resolver: &Resolver,
+    ctx: &Context,
+    output: &mut Output,
 ) -> Result<()> {
-    let mut file_path = path.to_owned();
-    if is_mod {
-        file_path.push("mod.rs");
-    } else {
-        file_path.push(&format!("{}.rs", name));
+    let file_path = match is_mod {
+        true => path.join("mod.rs"),
+        false => path.join(format!("{}.rs", name)),
+    };
+
+    if let Some(target) = ctx.reexports.get(&SchemaType::from(obj).path()) {
+        return generate_reexport(&file_path, path, is_mod, obj, mods, target, output);
     }
 
     let mut namespace = Namespace::new("");
+    namespace.reserve(&SchemaType::from(obj));
 
-    add_type_ref(&obj, resolver, &mut namespace)?;
+    let inner = add_type_ref(obj, ctx.resolver, &mut namespace)?;
 
-    let props_string = generate_props(&obj.props, resolver, &mut namespace)?;
+    let props_string = generate_props(obj, ctx, &mut namespace)?;
 
-    // IF this obj has a type_ref (rather than props), then resolve the ref
-    if let Some(schema_type) = resolver.resolve(&TypeRef::from_ref(obj.type_ref.clone())) {
-        namespace.add_type(&schema_type)?;
+    for (path, alias) in namespace.aliases() {
+        ctx.collisions.borrow_mut().push(Collision {
+            path: SchemaType::from(obj).path(),
+            name: path,
+            renamed: alias,
+            pointer: obj.pointer.clone().unwrap_or_default(),
+        });
     }
 
     let use_types = namespace.use_types();
@@ -68,8 +51,14 @@ pub(super) fn generate_struct(
     let use_supers = namespace.use_supers(&obj.ns, &obj.name, "")?;
 
     //--------------- Build the actual file ----------------
-    let mut buffer = File::create(file_path)?;
+    let mut buffer = String::new();
 
+    if is_mod {
+        let mut doc = DocComment::new();
+        doc.text(obj.title.as_deref().unwrap_or(&obj.name))
+            .markdown(&format!("See {}.", link(&obj.name)));
+        writeln!(buffer, "{}", doc.render("//!"))?;
+    }
     writeln!(buffer, "use serde::{{Deserialize, Serialize}};")?;
     if obj.has_options() {
         writeln!(buffer, "use serde_with::skip_serializing_none;")?;
@@ -90,6 +79,13 @@ pub(super) fn generate_struct(
     if let Some(oscal_types) = use_types {
         writeln!(buffer, "use oscal_types::{};\n", oscal_types)?;
     }
+    let use_externals = namespace.use_externals();
+    for external in &use_externals {
+        writeln!(buffer, "use {};", external)?;
+    }
+    if !use_externals.is_empty() {
+        writeln!(buffer)?;
+    }
     if !use_crates.is_empty() {
         writeln!(buffer, "use crate::{};\n", &use_crates)?;
     }
@@ -101,35 +97,86 @@ pub(super) fn generate_struct(
             writeln!(buffer, "use super::{};\n", &use_supers)?;
         }
     }
-    writeln!(buffer, "/// {}\n///", &obj.name)?;
-
-    if let Some(desc) = &obj.description {
-        for line in split_str_on_words(desc) {
-            writeln!(buffer, "/// {}", line)?;
-        }
-    }
-    if let Some(id) = &obj.id {
-        writeln!(buffer, "/// $id: {}", id)?;
-    }
+    write!(
+        buffer,
+        "{}",
+        struct_doc(obj, inner.as_deref()).render("///")
+    )?;
     if has_options(obj) {
         writeln!(buffer, "#[skip_serializing_none]")?;
     }
 
-    writeln!(
-        buffer,
-        "#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]"
-    )?;
+    let decoration = ctx.decorations.get(&SchemaType::from(obj));
+    writeln!(buffer, "#[derive({})]", decoration.derives.join(", "))?;
+    for attribute in &decoration.attributes {
+        writeln!(buffer, "{}", attribute)?;
+    }
     if obj._type == Some("string".to_owned()) {
         format_string_struct(&mut buffer, &obj.name)?;
-    } else if obj.type_ref.is_some() {
-        format_ref_struct(&mut buffer, &obj.name, &obj.type_ref, resolver)?;
+    } else if let Some(inner) = &inner {
+        format_ref_struct(&mut buffer, &obj.name, &obj.type_ref, inner, ctx)?;
     } else {
         format_prop_struct(&mut buffer, &obj.name, props_string)?;
+        format_json_names_test(&mut buffer, obj)?;
+    }
+
+    output.add(&file_path, buffer);
+    Ok(())
+}
+
+/// A type unchanged from an earlier schema version, or identical to another
+/// type, re-exported from there, under its own name
+fn generate_reexport(
+    file_path: &Path,
+    path: &Path,
+    is_mod: bool,
+    obj: &SchemaObject,
+    mods: Option<&Vec<&str>>,
+    target: &str,
+    output: &mut Output,
+) -> Result<()> {
+    let mut buffer = String::new();
+    if is_mod {
+        let mut doc = DocComment::new();
+        doc.text(obj.title.as_deref().unwrap_or(&obj.name))
+            .markdown(&format!("See {}.", link(&obj.name)));
+        writeln!(buffer, "{}", doc.render("//!"))?;
+    }
+    for name in mods.into_iter().flatten() {
+        if !path.ends_with(name) {
+            writeln!(buffer, "pub mod {};", name)?;
+        }
+    }
+    if mods.is_some() {
+        writeln!(buffer)?;
+    }
+    let name = SchemaType::from(obj).name;
+    match target.rsplit("::").next() == Some(name.as_str()) {
+        true => writeln!(buffer, "pub use crate::{};", target)?,
+        false => writeln!(buffer, "pub use crate::{} as {};", target, name)?,
     }
 
+    output.add(file_path, buffer);
     Ok(())
 }
 
+/// The title, or name, then the description.  Newtypes link to the type
+/// they wrap, by the name it is imported as.
+fn struct_doc(obj: &SchemaObject, inner: Option<&str>) -> DocComment {
+    let mut doc = DocComment::new();
+    doc.text(obj.title.as_deref().unwrap_or(&obj.name));
+    if let Some(description) = &obj.description {
+        doc.text(description);
+    }
+    if let Some(id) = &obj.id {
+        doc.schema(&code("$id"), &code(id));
+    }
+    if let Some(inner) = inner {
+        doc.schema("Wraps", &link(inner));
+    }
+    doc
+}
+
 fn has_options(obj: &SchemaObject) -> bool {
     let Some(props) = &obj.props else {
         return false;
@@ -142,110 +189,92 @@ fn has_options(obj: &SchemaObject) -> bool {
     false
 }
 
-fn add_type_ref(obj: &SchemaObject, resolver: &Resolver, namespace: &mut Namespace) -> Result<()> {
+/// Import what a newtype needs: the crate's `Error`, which keeps its name,
+/// and the type it wraps.  Returns the name the wrapped type is imported as.
+fn add_type_ref(
+    obj: &SchemaObject,
+    resolver: &Resolver,
+    namespace: &mut Namespace,
+) -> Result<Option<String>> {
     if obj._type == Some("string".to_string()) {
-        namespace.add_type(&SchemaType {
-            ns: "error".to_string(),
-            name: "Error".to_string(),
-        })?;
-        return Ok(());
+        namespace.add_type(&SchemaType::new("error", "Error"))?;
+        return Ok(None);
     }
 
     if obj.type_ref.is_none() {
-        return Ok(());
+        return Ok(None);
     }
 
     let Some(schema_type) = resolver.resolve(&TypeRef::from_ref(obj.type_ref.clone())) else {
         return Err(Error::ResolverFailure.into());
     };
 
-    namespace.add_type(&schema_type)?;
-
-    // Add the crate's Error
-    namespace.add_type(&SchemaType {
-        ns: "error".to_string(),
-        name: "Error".to_string(),
-    })?;
-
-    Ok(())
+    namespace.add_type(&SchemaType::new("error", "Error"))?;
+    Ok(Some(namespace.add_type(&schema_type)?))
 }
 
-fn format_string_struct(buffer: &mut File, name: &str) -> Result<()> {
-    writeln!(buffer, "pub struct {}(String);", name)?;
-
-    writeln!(buffer)?;
-    // Deref
-    writeln!(
-        buffer,
-        r##"impl std::ops::Deref for {name} {{
-    type Target = str;
-    fn deref(&self) -> &Self::Target {{
-            &self.0
-    }}
-}}"##
-    )?;
-
-    // TryFrom
-    writeln!(
-        buffer,
-        r##"
-impl TryFrom<&str> for {name} {{
-    type Error = Error;
-    fn try_from(value: &str) -> Result<Self, Self::Error> {{
-        Ok(Self(value.to_string()))
-    }}
-}}
-"##
-    )?;
-
-    Ok(())
+fn format_string_struct(buffer: &mut String, name: &str) -> Result<()> {
+    generate_newtype(buffer, name, "String", &NewtypeKind::String)
 }
 
 fn format_ref_struct(
-    buffer: &mut File,
+    buffer: &mut String,
     name: &str,
     _ref: &Option<String>,
-    resolver: &Resolver,
+    inner: &str,
+    ctx: &Context,
 ) -> Result<()> {
-    let Some(schema_type) = resolver.resolve(&TypeRef::from_ref(_ref.to_owned())) else {
-        eprintln!("Failed to resolve {:?}", _ref);
+    let Some(schema_type) = ctx.resolver.resolve(&TypeRef::from_ref(_ref.to_owned())) else {
         return Err(Error::ResolverFailure.into());
     };
 
-    writeln!(buffer, "pub struct {}({});", name, &schema_type.name)?;
+    let kind = match get_ref_type(&schema_type.name) {
+        Ok(ref_type) if !schema_type.external => NewtypeKind::Datatype {
+            ref_type: ref_type.to_string(),
+        },
+        _ => NewtypeKind::Opaque {
+            text: is_text(&schema_type, &ctx.graph),
+        },
+    };
 
-    if let Ok(ref_type) = get_ref_type(&schema_type.name) {
-        writeln!(buffer)?;
-        // Deref
-        writeln!(
-            buffer,
-            r##"impl std::ops::Deref for {name} {{
-    type Target = {ref_type};
-    fn deref(&self) -> &Self::Target {{
-            self.0.deref()
-    }}
-}}"##
-        )?;
-
-        // TryFrom
-        writeln!(
-            buffer,
-            r##"
-impl TryFrom<&{ref_type}> for {name} {{
-    type Error = Error;
-    fn try_from(value: &{ref_type}) -> Result<Self, Self::Error> {{
-        Ok(Self({}::try_from(value)?))
-    }}
-}}
-"##,
-            &schema_type.name
-        )?;
-    }
+    generate_newtype(buffer, name, inner, &kind)
+}
 
-    Ok(())
+/// Can the type be built from, and displayed as, text.  That is true for
+/// `String`, the `oscal_types` datatypes, and newtypes that end up wrapping
+/// them.  Types outside of the crate, such as a struct bound by `[types]`,
+/// may not be `FromStr` and `Display`, so they aren't text.
+fn is_text(schema_type: &SchemaType, graph: &TypeGraph) -> bool {
+    if schema_type.external {
+        return false;
+    }
+    if schema_type.ns == "oscal_types" {
+        return true;
+    }
+    let mut path = schema_type.path();
+    let mut seen = vec![];
+    // Follow the chain of newtypes, guarding against cycles
+    while !seen.contains(&path) {
+        seen.push(path.clone());
+        let Some(node) = graph.get(&path) else {
+            return path == "String";
+        };
+        let [edge] = node.edges.as_slice() else {
+            return false;
+        };
+        if edge.prop.is_some() {
+            return false;
+        }
+        if !graph.contains(&edge.target) {
+            // A leaf: String, a datatype, or a type outside of the crate
+            return edge.target == "String" || edge.target.starts_with("oscal_types::");
+        }
+        path = edge.target.clone();
+    }
+    false
 }
 
-fn format_prop_struct(buffer: &mut File, name: &str, props_string: Option<String>) -> Result<()> {
+fn format_prop_struct(buffer: &mut String, name: &str, props_string: Option<String>) -> Result<()> {
     writeln!(buffer, r##"#[serde(rename_all = "kebab-case")]"##)?;
     writeln!(buffer, "pub struct {} {{", name)?;
 
@@ -257,3 +286,82 @@ fn format_prop_struct(buffer: &mut File, name: &str, props_string: Option<String
     writeln!(buffer, "}}")?;
     Ok(())
 }
+
+/// A test that the struct deserializes from exactly the JSON names in the
+/// schema, whatever the field names and renames
+fn format_json_names_test(buffer: &mut String, obj: &SchemaObject) -> Result<()> {
+    let json_names = obj
+        .props
+        .iter()
+        .flatten()
+        .map(|(_, prop)| format!("{:?}", prop.json_name))
+        .collect::<Vec<String>>();
+    writeln!(
+        buffer,
+        r##"
+#[cfg(test)]
+mod json_names {{
+    #[test]
+    fn test_json_names() {{
+        // Typed, since an empty slice couldn't be inferred
+        let expected: &[&str] = &[{}];
+        assert_eq!(
+            crate::introspect::field_names::<super::{}>(),
+            expected
+        );
+    }}
+}}"##,
+        json_names.join(", "),
+        obj.name
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Schema;
+
+    fn newtype(ns: &str, name: &str, type_ref: &str) -> SchemaObject {
+        SchemaObject {
+            type_ref: Some(type_ref.to_owned()),
+            ..SchemaObject::test_object(ns, name)
+        }
+    }
+
+    #[test]
+    fn test_is_text() {
+        let mut resolver = Resolver::new();
+        resolver.add_type("#token", SchemaType::new("oscal_types", "TokenDatatype"));
+        resolver.add_type("#metadata", SchemaType::external("my_oscal::Metadata"));
+        resolver.add_type("#control-id", SchemaType::new("control_id", "ControlId"));
+
+        let mut tree = Schema::new("", "", "");
+        for obj in [
+            newtype("control_id", "ControlId", "#token"),
+            newtype("part_id", "PartId", "#control-id"),
+            newtype("meta", "Meta", "#metadata"),
+        ] {
+            tree.add_object(&obj.ns.clone(), &obj).expect("oops");
+        }
+        let graph = TypeGraph::build(&tree, &resolver);
+
+        assert!(is_text(&SchemaType::new("part_id", "PartId"), &graph));
+        assert!(!is_text(&SchemaType::new("meta", "Meta"), &graph));
+        assert!(!is_text(
+            &SchemaType::external("my_oscal::Metadata"),
+            &graph
+        ));
+    }
+
+    #[test]
+    fn test_json_names_test() {
+        let mut buffer = String::new();
+        format_json_names_test(&mut buffer, &SchemaObject::test_object("empty", "Empty"))
+            .expect("oops");
+        // An empty slice needs its type
+        assert!(buffer.contains(
+            "        let expected: &[&str] = &[];\n        assert_eq!(\n            crate::introspect::field_names::<super::Empty>(),\n            expected\n        );"
+        ));
+    }
+}
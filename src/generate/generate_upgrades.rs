@@ -0,0 +1,715 @@
+//! Conversions from the types of one schema version to the next, in the
+//! `upgrade` module of each version but the first.
+//!
+//! Types whose fields all carry over, or were added as optional, get a
+//! `From`.  The others get an `upgrade` function, and a `TryFrom` that calls
+//! it.  What can't be converted, such as a field that was added as required,
+//! goes through a method of the version's `Migration` trait, which fails by
+//! default, naming the field.  Implement the trait for hand-written fixups.
+//! Newtypes are converted through serde, since their validation may have
+//! changed.
+use anyhow::Result;
+use convert_case::{Case, Casing};
+use indexmap::IndexMap;
+use std::collections::BTreeSet;
+use std::fmt::Write;
+use std::path::Path;
+
+use crate::{unprefixed, Error, Property, Schema, SchemaObject, SchemaType};
+
+use super::{Context, Output};
+
+/// How a field of the new type gets its value
+enum FieldUpgrade<'o> {
+    /// From the old field, converted by the upgrade of `path` if set
+    Convert {
+        old_prop: &'o Property,
+        path: Option<String>,
+    },
+    /// A field added as optional
+    Absent,
+    /// A `Migration` method
+    Hook,
+}
+
+/// The upgrades from the `old` version module to the `new` one
+struct Upgrades<'a> {
+    old: &'a str,
+    new: &'a str,
+    ctx: &'a Context<'a>,
+    /// The types of the new version that are not re-exported, and have a
+    /// type of the same path in the old version, keyed by path
+    pairs: IndexMap<String, (&'a SchemaObject, &'a SchemaObject)>,
+    /// The types whose upgrade can fail
+    fallible: BTreeSet<String>,
+    /// The `Migration` method names, keyed by type path and field
+    hooks: IndexMap<(String, String), String>,
+}
+
+pub fn generate_upgrades(
+    path: &Path,
+    old: (&str, &Schema),
+    new: (&str, &Schema),
+    ctx: &Context,
+    output: &mut Output,
+) -> Result<()> {
+    let upgrades = Upgrades::new(old, new, ctx);
+
+    let mut buffer = String::new();
+    writeln!(
+        buffer,
+        "//! Upgrades from `{}`, the previous version\n",
+        old.0
+    )?;
+    if !upgrades.fallible.is_empty() {
+        writeln!(buffer, "use crate::error::Error;\n")?;
+    }
+    upgrades.generate_migration(&mut buffer)?;
+    for (path, (old_obj, new_obj)) in &upgrades.pairs {
+        upgrades.generate_upgrade(&mut buffer, path, old_obj, new_obj)?;
+    }
+
+    output.add(&path.join("upgrade.rs"), buffer);
+    Ok(())
+}
+
+impl<'a> Upgrades<'a> {
+    fn new(old: (&'a str, &'a Schema), new: (&'a str, &'a Schema), ctx: &'a Context<'a>) -> Self {
+        let old_objects: IndexMap<String, &SchemaObject> = old
+            .1
+            .objects()
+            .into_iter()
+            .map(|obj| {
+                (
+                    unprefixed(old.0, &SchemaType::from(obj).path()).to_owned(),
+                    obj,
+                )
+            })
+            .collect();
+        let mut pairs = IndexMap::new();
+        for new_obj in new.1.objects() {
+            let path = SchemaType::from(new_obj).path();
+            if ctx.reexports.contains_key(&path) {
+                continue;
+            }
+            if let Some(old_obj) = old_objects.get(unprefixed(new.0, &path)) {
+                pairs.insert(path, (*old_obj, new_obj));
+            }
+        }
+
+        let mut result = Self {
+            old: old.0,
+            new: new.0,
+            ctx,
+            pairs,
+            fallible: BTreeSet::new(),
+            hooks: IndexMap::new(),
+        };
+        result.find_hooks();
+        result.find_fallible();
+        result
+    }
+
+    /// Name a `Migration` method for every field that needs one
+    fn find_hooks(&mut self) {
+        let mut hooks = IndexMap::new();
+        for (path, (old_obj, new_obj)) in &self.pairs {
+            let type_name = new_obj.name.to_case(Case::Snake);
+            let mut fields: Vec<&str> = new_props(new_obj)
+                .filter(|(name, prop)| {
+                    matches!(self.field(old_obj, name, prop), FieldUpgrade::Hook)
+                })
+                .map(|(name, _)| name.as_str())
+                .collect();
+            fields.extend(removed(old_obj, new_obj).map(|(name, _)| name.as_str()));
+
+            for field in fields {
+                let base = format!("{}_{}", type_name, field.to_case(Case::Snake));
+                let mut method = base.clone();
+                let mut count = 2;
+                while hooks.values().any(|taken| *taken == method) {
+                    method = format!("{}_{}", base, count);
+                    count += 1;
+                }
+                hooks.insert((path.to_owned(), field.to_owned()), method);
+            }
+        }
+        self.hooks = hooks;
+    }
+
+    /// A type's upgrade can fail if it is a newtype, needs a migration, or
+    /// has a field whose upgrade can fail
+    fn find_fallible(&mut self) {
+        let mut fallible: BTreeSet<String> = self
+            .pairs
+            .iter()
+            .filter(|(path, (_, new_obj))| {
+                is_newtype(new_obj) || self.hooks.keys().any(|(hook_path, _)| hook_path == *path)
+            })
+            .map(|(path, _)| path.to_owned())
+            .collect();
+        loop {
+            let more: Vec<String> = self
+                .pairs
+                .iter()
+                .filter(|(path, _)| !fallible.contains(*path))
+                .filter(|(_, (old_obj, new_obj))| {
+                    new_props(new_obj).any(|(name, prop)| {
+                        matches!(
+                            self.field(old_obj, name, prop),
+                            FieldUpgrade::Convert { path: Some(path), .. } if fallible.contains(&path)
+                        )
+                    })
+                })
+                .map(|(path, _)| path.to_owned())
+                .collect();
+            if more.is_empty() {
+                break;
+            }
+            fallible.extend(more);
+        }
+        self.fallible = fallible;
+    }
+
+    fn field<'o>(
+        &self,
+        old_obj: &'o SchemaObject,
+        name: &str,
+        new_prop: &Property,
+    ) -> FieldUpgrade<'o> {
+        let Some(old_prop) = old_obj.props.as_ref().and_then(|props| props.get(name)) else {
+            return match new_prop.optional {
+                true => FieldUpgrade::Absent,
+                false => FieldUpgrade::Hook,
+            };
+        };
+        let old_enums = old_prop.enums.clone().unwrap_or_default();
+        let enums_removed = new_prop
+            .enums
+            .as_ref()
+            .is_some_and(|enums| old_enums.iter().any(|value| !enums.contains(value)));
+        if old_prop.array != new_prop.array
+            || (old_prop.optional && !new_prop.optional)
+            || enums_removed
+        {
+            return FieldUpgrade::Hook;
+        }
+
+        let resolver = self.ctx.resolver;
+        let (Some(old_type), Some(new_type)) = (
+            resolver.resolve(&old_prop.type_ref),
+            resolver.resolve(&new_prop.type_ref),
+        ) else {
+            return FieldUpgrade::Hook;
+        };
+        let (old_path, new_path) = (old_type.path(), new_type.path());
+        if self.concrete(&old_path) == self.concrete(&new_path) {
+            return FieldUpgrade::Convert {
+                old_prop,
+                path: None,
+            };
+        }
+        // The new type is upgraded from the old one
+        let new_concrete = self.concrete(&new_path);
+        let upgrades_from = |old_obj: &SchemaObject| {
+            self.concrete(&SchemaType::from(old_obj).path()) == self.concrete(&old_path)
+        };
+        match self.pairs.get(new_concrete) {
+            Some((old_obj, _)) if upgrades_from(old_obj) => FieldUpgrade::Convert {
+                old_prop,
+                path: Some(new_concrete.to_owned()),
+            },
+            _ => FieldUpgrade::Hook,
+        }
+    }
+
+    /// The path of the type that is generated for `path`
+    fn concrete<'p>(&'p self, path: &'p str) -> &'p str {
+        self.ctx
+            .reexports
+            .get(path)
+            .map(String::as_str)
+            .unwrap_or(path)
+    }
+
+    fn generate_migration(&self, buffer: &mut String) -> Result<()> {
+        writeln!(
+            buffer,
+            r##"/// Hand-written fixups for what can't be upgraded from `{}`
+/// automatically.  Each method gets the old value, and returns the value
+/// of the new field.  Methods for removed fields check that nothing is
+/// lost.  The default methods fail, naming the field.
+pub trait Migration {{"##,
+            self.old
+        )?;
+        for ((path, field), method) in &self.hooks {
+            let (old_obj, new_obj) = self.pairs[path];
+            let old_type = format!("crate::{}", SchemaType::from(old_obj).path());
+            let error = format!(
+                r#"Error::Upgrade("{}.{}".to_owned())"#,
+                unprefixed(self.new, path),
+                field
+            );
+            let new_prop = new_obj.props.as_ref().and_then(|props| props.get(field));
+            let (returns, body) = match new_prop {
+                Some(new_prop) => (
+                    self.field_type(path, field, new_prop),
+                    format!("Err({})", error),
+                ),
+                None => {
+                    // A hook on a field missing from the new type is one
+                    // that was removed, so the old type has it
+                    let old_prop = old_obj
+                        .props
+                        .as_ref()
+                        .and_then(|props| props.get(field))
+                        .ok_or_else(|| Error::AttributeNotFound(format!("{}.{}", path, field)))?;
+                    let empty = match (old_prop.optional, old_prop.array) {
+                        (true, _) => format!("old.{}.is_none()", old_prop.name),
+                        (false, true) => format!("old.{}.is_empty()", old_prop.name),
+                        (false, false) => "false".to_owned(),
+                    };
+                    let body = format!(
+                        "match {} {{\n            true => Ok(()),\n            false => Err({}),\n        }}",
+                        empty, error
+                    );
+                    ("()".to_owned(), body)
+                }
+            };
+            let old_param = match body.contains("old.") {
+                true => "old",
+                false => "_old",
+            };
+            writeln!(
+                buffer,
+                "    fn {}(&self, {}: &{}) -> Result<{}, Error> {{\n        {}\n    }}",
+                method, old_param, old_type, returns, body
+            )?;
+        }
+        writeln!(buffer, "}}\n")?;
+        writeln!(
+            buffer,
+            "/// The default migration, which fails where one is needed\npub struct NoMigration;\n\nimpl Migration for NoMigration {{}}"
+        )?;
+        Ok(())
+    }
+
+    fn generate_upgrade(
+        &self,
+        buffer: &mut String,
+        path: &str,
+        old_obj: &SchemaObject,
+        new_obj: &SchemaObject,
+    ) -> Result<()> {
+        let old_type = format!("crate::{}", SchemaType::from(old_obj).path());
+        let new_type = format!("crate::{}", path);
+        let fallible = self.fallible.contains(path);
+
+        writeln!(buffer)?;
+        if is_newtype(new_obj) {
+            writeln!(
+                buffer,
+                r##"impl {new_type} {{
+    /// Upgrade from `{old}`, validating the value again
+    pub fn upgrade(old: {old_type}, _migration: &impl Migration) -> Result<Self, Error> {{
+        serde_json::to_value(old)
+            .and_then(serde_json::from_value)
+            .map_err(|e| Error::Upgrade(format!("{relative}: {{}}", e)))
+    }}
+}}"##,
+                old = self.old,
+                relative = unprefixed(self.new, path),
+            )?;
+            return self.generate_try_from(buffer, &old_type, &new_type);
+        }
+
+        let mut hooks = String::new();
+        let mut fields = String::new();
+        for (name, new_prop) in new_props(new_obj) {
+            let value = match self.field(old_obj, name, new_prop) {
+                FieldUpgrade::Absent => "None".to_owned(),
+                FieldUpgrade::Hook => {
+                    let method = &self.hooks[&(path.to_owned(), name.to_owned())];
+                    writeln!(
+                        hooks,
+                        "        let {} = migration.{}(&old)?;",
+                        method, method
+                    )?;
+                    method.to_owned()
+                }
+                FieldUpgrade::Convert {
+                    old_prop,
+                    path: upgrade,
+                } => self.convert(path, name, new_prop, old_prop, upgrade.as_deref(), old_obj),
+            };
+            writeln!(fields, "            {}: {},", new_prop.name, value)?;
+        }
+        for (name, _) in removed(old_obj, new_obj) {
+            let method = &self.hooks[&(path.to_owned(), name.to_owned())];
+            writeln!(hooks, "        migration.{}(&old)?;", method)?;
+        }
+        let old_param = match fields.contains("old.") || !hooks.is_empty() {
+            true => "old",
+            false => "_old",
+        };
+
+        match fallible {
+            true => {
+                writeln!(
+                    buffer,
+                    r##"impl {new_type} {{
+    /// Upgrade from `{old}`, with `migration` for what can't be upgraded
+    /// automatically
+    pub fn upgrade({old_param}: {old_type}, migration: &impl Migration) -> Result<Self, Error> {{
+{hooks}        Ok(Self {{
+{fields}        }})
+    }}
+}}"##,
+                    old = self.old,
+                )?;
+                self.generate_try_from(buffer, &old_type, &new_type)
+            }
+            false => {
+                writeln!(
+                    buffer,
+                    r##"impl From<{old_type}> for {new_type} {{
+    fn from({old_param}: {old_type}) -> Self {{
+        Self {{
+{fields}        }}
+    }}
+}}"##
+                )?;
+                Ok(())
+            }
+        }
+    }
+
+    fn generate_try_from(&self, buffer: &mut String, old_type: &str, new_type: &str) -> Result<()> {
+        writeln!(
+            buffer,
+            r##"
+impl TryFrom<{old_type}> for {new_type} {{
+    type Error = Error;
+
+    fn try_from(old: {old_type}) -> Result<Self, Error> {{
+        Self::upgrade(old, &NoMigration)
+    }}
+}}"##
+        )?;
+        Ok(())
+    }
+
+    /// The expression that converts the old field to the new one
+    fn convert(
+        &self,
+        path: &str,
+        name: &str,
+        new_prop: &Property,
+        old_prop: &Property,
+        upgrade: Option<&str>,
+        old_obj: &SchemaObject,
+    ) -> String {
+        let old_path = SchemaType::from(old_obj).path();
+        let old_boxed = self.ctx.graph.is_boxed(&old_path, name);
+        let new_boxed = self.ctx.graph.is_boxed(path, name);
+        let fallible = upgrade.is_some_and(|upgrade| self.fallible.contains(upgrade));
+
+        let element = |value: &str| {
+            let unboxed = match old_boxed {
+                true => format!("*{}", value),
+                false => value.to_owned(),
+            };
+            let converted = match upgrade {
+                None if old_boxed == new_boxed => return value.to_owned(),
+                None => unboxed,
+                Some(upgrade) if fallible => {
+                    format!("crate::{}::upgrade({}, migration)?", upgrade, unboxed)
+                }
+                Some(upgrade) => format!("crate::{}::from({})", upgrade, unboxed),
+            };
+            match new_boxed {
+                true => format!("Box::new({})", converted),
+                false => converted,
+            }
+        };
+
+        let field = format!("old.{}", old_prop.name);
+        if element("x") == "x" {
+            return match (old_prop.optional, new_prop.optional) {
+                (false, true) => format!("Some({})", field),
+                _ => field,
+            };
+        }
+        let map = |each: &str| match fallible {
+            true => format!(
+                "{}.into_iter().map(|x| Ok::<_, Error>({})).collect::<Result<Vec<_>, Error>>()",
+                each,
+                element("x")
+            ),
+            false => format!("{}.into_iter().map(|x| {}).collect()", each, element("x")),
+        };
+        let try_ = match fallible {
+            true => "?",
+            false => "",
+        };
+        let moved = match (old_prop.optional, new_prop.array, fallible) {
+            (false, false, _) => element(&field),
+            (false, true, _) => format!("{}{}", map(&field), try_),
+            (true, false, false) => format!("{}.map(|x| {})", field, element("x")),
+            (true, false, true) => format!(
+                "{}.map(|x| Ok::<_, Error>({})).transpose()?",
+                field,
+                element("x")
+            ),
+            (true, true, false) => format!("{}.map(|v| {})", field, map("v")),
+            (true, true, true) => format!("{}.map(|v| {}).transpose()?", field, map("v")),
+        };
+        match (old_prop.optional, new_prop.optional) {
+            (false, true) => format!("Some({})", moved),
+            _ => moved,
+        }
+    }
+
+    /// The Rust type of a field of the new type
+    fn field_type(&self, path: &str, name: &str, prop: &Property) -> String {
+        let base = match self.ctx.resolver.resolve(&prop.type_ref) {
+            Some(schema_type) => rust_path(&schema_type),
+            None => "()".to_owned(),
+        };
+        let base = match self.ctx.graph.is_boxed(path, name) {
+            true => format!("Box<{}>", base),
+            false => base,
+        };
+        let base = match prop.array {
+            true => format!("Vec<{}>", base),
+            false => base,
+        };
+        match prop.optional {
+            true => format!("Option<{}>", base),
+            false => base,
+        }
+    }
+}
+
+/// The path of a type, from anywhere in the crate
+fn rust_path(schema_type: &SchemaType) -> String {
+    match (schema_type.external, schema_type.ns.as_str()) {
+        (_, "") => schema_type.name.clone(),
+        (true, ns) | (false, ns @ "oscal_types") => format!("{}::{}", ns, schema_type.name),
+        (false, ns) => format!("crate::{}::{}", ns, schema_type.name),
+    }
+}
+
+fn is_newtype(obj: &SchemaObject) -> bool {
+    obj.props.is_none() && (obj._type.as_deref() == Some("string") || obj.type_ref.is_some())
+}
+
+fn new_props(obj: &SchemaObject) -> impl Iterator<Item = (&String, &Property)> {
+    obj.props.iter().flatten()
+}
+
+/// The fields of the old type that the new one doesn't have
+fn removed<'o>(
+    old_obj: &'o SchemaObject,
+    new_obj: &'o SchemaObject,
+) -> impl Iterator<Item = (&'o String, &'o Property)> {
+    old_obj.props.iter().flatten().filter(move |(name, _)| {
+        !new_obj
+            .props
+            .as_ref()
+            .is_some_and(|props| props.contains_key(*name))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{merge_versions, Config, Ir, Resolver};
+
+    /// A field: name, `$ref`, optional, array and enum values
+    type Field<'f> = (&'f str, &'f str, bool, bool, &'f [&'f str]);
+
+    fn props(fields: &[Field]) -> Option<IndexMap<String, Property>> {
+        let props = fields
+            .iter()
+            .map(|(field, _ref, optional, array, enums)| {
+                let prop = Property {
+                    optional: *optional,
+                    array: *array,
+                    enums: match enums.is_empty() {
+                        true => None,
+                        false => Some(enums.iter().map(|value| value.to_string()).collect()),
+                    },
+                    ..Property::test_property(field, &field.replace('_', "-"), _ref)
+                };
+                (field.to_string(), prop)
+            })
+            .collect();
+        Some(props)
+    }
+
+    fn ir(version: &str, objects: Vec<SchemaObject>) -> Ir {
+        let mut resolver = Resolver::new();
+        let mut schema = Schema::new("", version, "");
+        for obj in objects {
+            resolver.add_type(&format!("#{}", obj.ns), SchemaType::from(&obj));
+            schema.add_object(&obj.ns.clone(), &obj).expect("oops");
+        }
+        Ir { schema, resolver }
+    }
+
+    /// The upgrade module generated from `old` to `new`
+    fn upgrade(old: Vec<SchemaObject>, new: Vec<SchemaObject>) -> String {
+        let ir = merge_versions(vec![ir("1.0.0", old), ir("2.0.0", new)]).expect("oops");
+        let ctx = Context::new(&ir.schema, &ir.resolver, &Config::default()).expect("oops");
+        let versions = ir.schema.version_trees();
+        let mut output = Output::new();
+        let path = Path::new("src/v2_0_0");
+        generate_upgrades(path, versions[0], versions[1], &ctx, &mut output).expect("oops");
+        let (_, upgrade) = output.iter().next().expect("oops");
+        upgrade.to_owned()
+    }
+
+    const TOKEN: &str = "#/definitions/TokenDatatype";
+
+    #[test]
+    fn test_compatible_upgrades() {
+        let upgrade = upgrade(
+            vec![SchemaObject {
+                id: Some("#part".to_owned()),
+                _type: Some("object".to_owned()),
+                props: props(&[("name", TOKEN, false, false, &["a", "b"])]),
+                ..SchemaObject::test_object("part", "Part")
+            }],
+            vec![SchemaObject {
+                id: Some("#part".to_owned()),
+                _type: Some("object".to_owned()),
+                props: props(&[
+                    ("name", TOKEN, false, false, &["a", "b", "c"]),
+                    ("remarks", TOKEN, true, false, &[]),
+                ]),
+                ..SchemaObject::test_object("part", "Part")
+            }],
+        );
+        // An enum superset, and an added optional field
+        assert!(upgrade.contains(
+            "impl From<crate::v1_0_0::part::Part> for crate::v2_0_0::part::Part {\n    fn from(old: crate::v1_0_0::part::Part) -> Self {\n        Self {\n            name: old.name,\n            remarks: None,\n        }"
+        ));
+        assert!(!upgrade.contains("TryFrom"));
+        assert!(!upgrade.contains("use crate::error::Error;"));
+    }
+
+    #[test]
+    fn test_fallible_upgrades() {
+        let old = vec![
+            SchemaObject {
+                id: Some("#group".to_owned()),
+                _type: Some("object".to_owned()),
+                props: props(&[
+                    ("id", TOKEN, false, false, &[]),
+                    ("class", TOKEN, true, false, &[]),
+                    ("kind", TOKEN, false, false, &["x", "y"]),
+                ]),
+                ..SchemaObject::test_object("group", "Group")
+            },
+            SchemaObject {
+                id: Some("#catalog".to_owned()),
+                _type: Some("object".to_owned()),
+                props: props(&[("groups", "#group", true, true, &[])]),
+                ..SchemaObject::test_object("catalog", "Catalog")
+            },
+        ];
+        let new = vec![
+            SchemaObject {
+                id: Some("#group".to_owned()),
+                _type: Some("object".to_owned()),
+                props: props(&[
+                    ("id", TOKEN, false, false, &[]),
+                    ("kind", TOKEN, false, false, &["x"]),
+                    ("ns", TOKEN, false, false, &[]),
+                ]),
+                ..SchemaObject::test_object("group", "Group")
+            },
+            SchemaObject {
+                id: Some("#catalog".to_owned()),
+                _type: Some("object".to_owned()),
+                props: props(&[("groups", "#group", true, true, &[])]),
+                ..SchemaObject::test_object("catalog", "Catalog")
+            },
+        ];
+        let upgrade = upgrade(old, new);
+
+        assert!(upgrade.starts_with(
+            "//! Upgrades from `v1_0_0`, the previous version\n\nuse crate::error::Error;\n"
+        ));
+        // A removed enum value and a new required field need a migration,
+        // which fails by default
+        assert!(upgrade.contains(
+            "    fn group_kind(&self, _old: &crate::v1_0_0::group::Group) -> Result<oscal_types::TokenDatatype, Error> {\n        Err(Error::Upgrade(\"group::Group.kind\".to_owned()))\n    }"
+        ));
+        assert!(upgrade.contains("Err(Error::Upgrade(\"group::Group.ns\".to_owned()))"));
+        // A removed field checks that nothing is lost
+        assert!(upgrade.contains(
+            "    fn group_class(&self, old: &crate::v1_0_0::group::Group) -> Result<(), Error> {\n        match old.class.is_none() {"
+        ));
+        assert!(upgrade.contains(
+            "        let group_kind = migration.group_kind(&old)?;\n        let group_ns = migration.group_ns(&old)?;\n        migration.group_class(&old)?;\n        Ok(Self {\n            id: old.id,\n            kind: group_kind,\n            ns: group_ns,\n        })"
+        ));
+        assert!(upgrade.contains(
+            "impl TryFrom<crate::v1_0_0::group::Group> for crate::v2_0_0::group::Group {"
+        ));
+
+        // Catalog holds groups, so its upgrade can fail too
+        assert!(upgrade.contains(
+            "    pub fn upgrade(old: crate::v1_0_0::catalog::Catalog, migration: &impl Migration) -> Result<Self, Error> {"
+        ));
+        assert!(upgrade.contains(
+            "groups: old.groups.map(|v| v.into_iter().map(|x| Ok::<_, Error>(crate::v2_0_0::group::Group::upgrade(x, migration)?)).collect::<Result<Vec<_>, Error>>()).transpose()?,"
+        ));
+        assert!(upgrade.contains(
+            "impl TryFrom<crate::v1_0_0::catalog::Catalog> for crate::v2_0_0::catalog::Catalog {"
+        ));
+    }
+
+    #[test]
+    fn test_boxed_upgrades() {
+        let node = |fields: &[Field]| {
+            let mut all: Vec<Field> = vec![("next_node", "#node", true, false, &[])];
+            all.extend_from_slice(fields);
+            SchemaObject {
+                id: Some("#node".to_owned()),
+                _type: Some("object".to_owned()),
+                props: props(&all),
+                ..SchemaObject::test_object("node", "Node")
+            }
+        };
+        let upgrade = upgrade(
+            vec![node(&[])],
+            vec![node(&[("label", TOKEN, true, false, &[])])],
+        );
+        // The field named next-node in JSON is boxed, since Node holds itself
+        assert!(upgrade.contains(
+            "next_node: old.next_node.map(|x| Box::new(crate::v2_0_0::node::Node::from(*x))),"
+        ));
+        assert!(upgrade.contains("label: None,"));
+    }
+
+    #[test]
+    fn test_rust_path() {
+        assert_eq!(rust_path(&SchemaType::new("", "String")), "String");
+        assert_eq!(
+            rust_path(&SchemaType::new("oscal_types", "TokenDatatype")),
+            "oscal_types::TokenDatatype"
+        );
+        assert_eq!(
+            rust_path(&SchemaType::new("v1_1_2::oscal_catalog::group", "Group")),
+            "crate::v1_1_2::oscal_catalog::group::Group"
+        );
+        assert_eq!(
+            rust_path(&SchemaType::external(
+                "chrono::DateTime<chrono::FixedOffset>"
+            )),
+            "chrono::DateTime<chrono::FixedOffset>"
+        );
+    }
+}
@@ -0,0 +1,159 @@
+//! The version modules of a crate generated from several schema versions,
+//! and the crate level `OscalDocument` that picks one.
+use anyhow::Result;
+use std::fmt::Write;
+use std::path::Path;
+
+use crate::{Config, Resolver, Schema};
+
+use super::{document_models, generate_oscal_document, DocComment, ModelFeatures, Output};
+
+/// The `mod.rs` of a version module, with the version and the
+/// `OscalDocument` of that version.  All but the first version have an
+/// `upgrade` module, from the `previous` one.
+pub fn generate_version(
+    path: &Path,
+    tree: &Schema,
+    previous: Option<&str>,
+    features: Option<&ModelFeatures>,
+    resolver: &Resolver,
+    config: &Config,
+    output: &mut Output,
+) -> Result<()> {
+    let mut buffer = String::new();
+
+    let mut doc = DocComment::new();
+    doc.markdown(&format!("The OSCAL {} model", tree.version));
+    writeln!(buffer, "{}", doc.render("//!"))?;
+
+    for mod_name in tree.keys() {
+        writeln!(buffer, "pub mod {};", mod_name)?;
+    }
+    if let Some(previous) = previous {
+        if let Some(features) = features {
+            writeln!(buffer, "{}", features.cfg(previous))?;
+        }
+        writeln!(buffer, "pub mod upgrade;")?;
+    }
+    writeln!(buffer)?;
+    writeln!(buffer, "/// The OSCAL schema version of this module")?;
+    writeln!(
+        buffer,
+        r#"pub static SCHEMA_VERSION: &str = "{}";"#,
+        tree.version
+    )?;
+
+    let models = document_models(tree, resolver, &config.identifiers)?;
+    generate_oscal_document(&mut buffer, &models, None)?;
+
+    output.add(&path.join("mod.rs"), buffer);
+    Ok(())
+}
+
+/// An `OscalDocument` of any version, parsed with the version module named
+/// by the document's `metadata.oscal-version`
+pub fn generate_version_loader(
+    buffer: &mut String,
+    versions: &[(&str, &Schema)],
+    features: Option<&ModelFeatures>,
+) -> Result<()> {
+    let cfg = |module: &str| match features {
+        Some(features) => format!("{}\n", features.cfg(module)),
+        None => String::new(),
+    };
+
+    writeln!(
+        buffer,
+        r##"
+
+use serde::Serialize;
+
+/// A document of any of the OSCAL versions of this crate.  Variants are
+/// named for their module, such as `V1_1_2`, since `V112` could be 1.1.2
+/// or 11.2.
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum OscalDocument {{"##
+    )?;
+    for (module, _) in versions {
+        write!(buffer, "{}", indent(&cfg(module)))?;
+        writeln!(
+            buffer,
+            "    {}({}::OscalDocument),",
+            module.to_uppercase(),
+            module
+        )?;
+    }
+    writeln!(buffer, "}}")?;
+
+    writeln!(
+        buffer,
+        r##"
+impl OscalDocument {{
+    /// Parse a document from JSON, with the module of its
+    /// `metadata.oscal-version`.
+    pub fn from_json_str(json: &str) -> Result<Self, crate::error::Error> {{
+        match oscal_version(json)?.as_str() {{"##
+    )?;
+    for (module, tree) in versions {
+        write!(buffer, "{}", indent(&indent(&indent(&cfg(module)))))?;
+        writeln!(
+            buffer,
+            r#"            "{}" => {}::OscalDocument::from_json_str(json).map(Self::{}),"#,
+            tree.version,
+            module,
+            module.to_uppercase()
+        )?;
+    }
+    writeln!(
+        buffer,
+        r##"            version => Err(crate::error::Error::UnsupportedVersion(version.to_owned())),
+        }}
+    }}
+}}
+
+/// The `metadata.oscal-version` of a JSON document, such as `1.1.2`
+pub fn oscal_version(json: &str) -> Result<String, crate::error::Error> {{
+    let document: serde_json::Value =
+        serde_json::from_str(json).map_err(|e| crate::error::Error::Parse(e.to_string()))?;
+    document
+        .as_object()
+        .into_iter()
+        .flat_map(|document| document.values())
+        .find_map(|model| model.get("metadata")?.get("oscal-version")?.as_str())
+        .map(str::to_owned)
+        .ok_or(crate::error::Error::Parse(
+            "missing metadata.oscal-version".to_owned(),
+        ))
+}}"##
+    )?;
+    Ok(())
+}
+
+fn indent(lines: &str) -> String {
+    lines
+        .lines()
+        .map(|line| format!("    {}\n", line))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_loader() {
+        let old = Schema::new("", "1.0.4", "");
+        let new = Schema::new("", "1.1.2", "");
+        let mut buffer = String::new();
+        generate_version_loader(&mut buffer, &[("v1_0_4", &old), ("v1_1_2", &new)], None)
+            .expect("oops");
+        assert!(buffer.contains(
+            "#[allow(non_camel_case_types)]\n#[derive(Debug, Clone, PartialEq, Serialize)]\n#[serde(untagged)]\npub enum OscalDocument {\n    V1_0_4(v1_0_4::OscalDocument),\n    V1_1_2(v1_1_2::OscalDocument),\n}"
+        ));
+        assert!(buffer.contains(
+            r#"            "1.1.2" => v1_1_2::OscalDocument::from_json_str(json).map(Self::V1_1_2),"#
+        ));
+    }
+}
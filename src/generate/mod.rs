@@ -1,40 +1,151 @@
+use decorations::*;
+use doc_comment::*;
+use features::*;
 use generate_cargo::*;
+use generate_document::*;
 use generate_mod::*;
+use generate_newtype::*;
 use generate_props::*;
 use generate_schema::*;
+pub use generate_static::read_static;
 use generate_static::*;
 use generate_struct::*;
+use generate_upgrades::*;
+use generate_versions::*;
 pub use name_space::Namespace;
+pub use output::Output;
 
+mod decorations;
+mod doc_comment;
+mod features;
 mod generate_cargo;
+mod generate_document;
 mod generate_mod;
+mod generate_newtype;
 mod generate_props;
 mod generate_schema;
 mod generate_static;
 mod generate_struct;
+mod generate_upgrades;
+mod generate_versions;
 mod name_space;
+mod output;
 
 use anyhow::Result;
-use std::fs::File;
-use std::io::prelude::*;
+use indexmap::IndexMap;
+use std::cell::RefCell;
+use std::fmt::Write;
 use std::path::{Path, PathBuf};
 
-use crate::{Resolver, Schema};
+use crate::{
+    identical_types, shared_types, Code, Collision, Config, Cycle, Diagnostics, Resolver, Schema,
+    TypeGraph, GENERATOR_VERSION,
+};
+
+/// Everything the generators need to know, beyond the object at hand
+pub struct Context<'a> {
+    pub resolver: &'a Resolver,
+    pub graph: TypeGraph,
+    /// Types that hold each other by value, and the fields boxed because of it
+    pub cycles: Vec<Cycle>,
+    pub decorations: Decorations,
+    /// Types that are re-exported rather than generated, keyed by path, with
+    /// the path they re-export.  These are types generated from several
+    /// schema versions that didn't change from an earlier version, and with
+    /// `dedup`, types identical to another.
+    pub reexports: IndexMap<String, String>,
+    /// Imported types that had to be aliased
+    pub collisions: RefCell<Vec<Collision>>,
+}
+
+impl<'a> Context<'a> {
+    pub fn new(tree: &Schema, resolver: &'a Resolver, config: &Config) -> Result<Self> {
+        let mut reexports = shared_types(tree, resolver);
+        let aliases = match config.dedup {
+            true => identical_types(tree, resolver, &reexports, config),
+            false => IndexMap::new(),
+        };
+        let mut graph = TypeGraph::build(tree, resolver);
+        graph.redirect(&aliases);
+        let cycles = graph.break_cycles()?;
+        reexports.extend(aliases);
+        let decorations = Decorations::new(&graph, config);
+        Ok(Self {
+            resolver,
+            graph,
+            cycles,
+            decorations,
+            reexports,
+            collisions: RefCell::new(vec![]),
+        })
+    }
+}
+
+/// Identifies the inputs a generated crate was produced from.
+pub struct Provenance {
+    /// Fingerprint of everything the output depends on: the input schema(s)
+    /// or IR, the config, the static files and the generator version
+    pub fingerprint: String,
+    /// Also record the time of generation.  This makes the output differ
+    /// on every run.
+    pub timestamp: bool,
+}
 
+/// Render the complete crate in memory.  Nothing is written to disk.
+/// Boxed cycles and name collisions go to `diagnostics`.
 pub fn generate(
-    path: PathBuf,
-    static_input: PathBuf,
+    static_input: &Path,
     tree: &Schema,
     resolver: &Resolver,
-) -> Result<()> {
-    let src_path = Path::new(&path).join("src").to_path_buf();
-    let lib_path = Path::new(&src_path).join("lib.rs").to_path_buf();
+    provenance: &Provenance,
+    config: &Config,
+    diagnostics: &mut Diagnostics,
+) -> Result<Output> {
+    let mut output = Output::new();
+    let src_path = PathBuf::from("src");
+    let lib_path = src_path.join("lib.rs");
+
+    let ctx = Context::new(tree, resolver, config)?;
+    for cycle in &ctx.cycles {
+        diagnostics.note(Code::BoxedCycle, &cycle.pointer, &cycle.to_string());
+    }
+    generate_schema(&src_path, tree, &ctx, &mut output)?;
+    let features = match config.model_features.unwrap_or(config.emit_cargo) {
+        true => Some(ModelFeatures::new(tree, &ctx.graph, &ctx.reexports)),
+        false => None,
+    };
+
+    let versions = tree.version_trees();
+    for (index, (module, version_tree)) in versions.iter().enumerate() {
+        let path = src_path.join(module);
+        let previous = index.checked_sub(1).map(|previous| versions[previous]);
+        generate_version(
+            &path,
+            version_tree,
+            previous.map(|(previous, _)| previous),
+            features.as_ref(),
+            resolver,
+            config,
+            &mut output,
+        )?;
+        if let Some(previous) = previous {
+            generate_upgrades(&path, previous, (module, version_tree), &ctx, &mut output)?;
+        }
+    }
 
-    generate_schema(&src_path, tree, resolver)?;
+    let mut collisions = tree.collisions();
+    collisions.append(&mut ctx.collisions.borrow_mut());
+    for collision in &collisions {
+        diagnostics.warn(
+            Code::NameCollision,
+            &collision.pointer,
+            &collision.to_string(),
+        );
+    }
 
-    let mut buffer = File::create(lib_path)?;
+    let mut buffer = String::new();
 
-    generate_lib_header(&mut buffer)?;
+    generate_lib_header(&mut buffer, provenance)?;
 
     writeln!(
         buffer,
@@ -45,82 +156,70 @@ pub fn generate(
         r#"pub static SCHEMA_VERSION: &str = "{}";"#,
         tree.version
     )?;
+    if !versions.is_empty() {
+        let list = versions
+            .iter()
+            .map(|(_, version_tree)| format!(r#""{}""#, version_tree.version))
+            .collect::<Vec<String>>()
+            .join(", ");
+        writeln!(
+            buffer,
+            "/// Every OSCAL schema version this code was generated from, oldest first"
+        )?;
+        writeln!(buffer, "pub static SCHEMA_VERSIONS: &[&str] = &[{}];", list)?;
+    }
+    writeln!(
+        buffer,
+        r#"/// SHA-256 of the inputs this code was generated from: the schema(s) or IR,
+/// the codegen config, the static files and the generator version"#
+    )?;
+    writeln!(
+        buffer,
+        r#"pub static SCHEMA_SHA256: &str = "{}";"#,
+        provenance.fingerprint
+    )?;
     writeln!(buffer)?;
 
     for mod_name in tree.keys() {
+        if let Some(features) = &features {
+            writeln!(buffer, "{}", features.cfg(mod_name))?;
+        }
         writeln!(buffer, "pub mod {};", mod_name)?;
     }
     writeln!(buffer, "pub mod error;")?;
+    writeln!(buffer, "#[cfg(test)]\nmod introspect;")?;
+
+    match versions.is_empty() {
+        true => {
+            let models = document_models(tree, resolver, &config.identifiers)?;
+            generate_oscal_document(&mut buffer, &models, features.as_ref())?;
+        }
+        false => generate_version_loader(&mut buffer, &versions, features.as_ref())?,
+    }
+    output.add(&lib_path, buffer);
 
-    generate_oscal_document(&mut buffer)?;
-
-    generate_static(&path, &static_input)?;
-    generate_cargo(path)?;
-    Ok(())
+    generate_static(static_input, &mut output)?;
+    generate_cargo(config, features.as_ref(), &mut output)?;
+    Ok(output)
 }
 
-fn generate_lib_header(buffer: &mut File) -> Result<()> {
+fn generate_lib_header(buffer: &mut String, provenance: &Provenance) -> Result<()> {
     writeln!(
         buffer,
         r##"//! OSCAL Lib
 //!
-//! This file was auto-generated at {}"##,
-        oscal_types::DateTimeDatatype::new().to_rfc2822()
+//! This file was auto-generated by oscal_codegen {}
+//! from inputs with fingerprint {}, see `SCHEMA_SHA256`"##,
+        GENERATOR_VERSION, provenance.fingerprint
     )?;
+    if provenance.timestamp {
+        writeln!(
+            buffer,
+            "//! This file was auto-generated at {}",
+            oscal_types::DateTimeDatatype::new().to_rfc2822()
+        )?;
+    }
     writeln!(buffer)?;
 
     Ok(())
 }
-
-fn generate_oscal_document(buffer: &mut File) -> Result<()> {
-    writeln!(
-        buffer,
-        r##"
-
-use serde::{{Deserialize, Serialize}};
-use serde_with::skip_serializing_none;
-
-use crate::{{
-    oscal_ap::assessment_plan::AssessmentPlan, oscal_ar::assessment_results::AssessmentResults,
-    oscal_catalog::catalog::Catalog,
-    oscal_component_definition::component_definition::ComponentDefinition,
-    oscal_poam::plan_of_action_and_milestones::PlanOfActionAndMilestones,
-    oscal_profile::profile::Profile,
-    oscal_ssp::system_security_plan::SystemSecurityPlan,
-}};
-
-    "##
-    )?;
-    writeln!(
-        buffer,
-        r##"#[skip_serializing_none]
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
-#[serde(rename_all = "kebab-case")]
-pub enum OscalDocumentType {{
-    Catalog(Box<Catalog>),
-    Profile(Box<Profile>),
-    ComponentDefinition(Box<ComponentDefinition>),
-    SystemSecurityPlan(Box<SystemSecurityPlan>),
-    AssessmentPlan(Box<AssessmentPlan>),
-    AssessmentResults(Box<AssessmentResults>),
-    PlanOfActionAndMilestones(Box<PlanOfActionAndMilestones>),
-}}
-"##
-    )?;
-
-    writeln!(buffer)?;
-
-    writeln!(
-        buffer,
-        r##"#[skip_serializing_none]
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
-pub struct OscalDocument {{
-    #[serde(rename = "$schema")]
-    pub schema: Option<String>,
-    #[serde(flatten)]
-    pub document: OscalDocumentType
-}}
-    "##
-    )?;
-    Ok(())
-}
@@ -1,6 +1,10 @@
 use anyhow::Result;
+use convert_case::{Case, Casing};
 use serde::{Deserialize, Serialize};
-use std::{cmp::Ordering, collections::BTreeMap};
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, BTreeSet},
+};
 
 use crate::SchemaType;
 
@@ -12,6 +16,13 @@ pub struct Namespace {
     pub name: String,
     pub entries: Vec<String>,
     pub subs: BTreeMap<String, Namespace>,
+    /// Full paths of types outside of the generated crate
+    #[serde(default)]
+    pub externals: BTreeSet<String>,
+    /// The name each type is known by in the module, keyed by that name.
+    /// Only used at the root.
+    #[serde(default)]
+    pub locals: BTreeMap<String, String>,
 }
 
 impl Namespace {
@@ -20,9 +31,59 @@ impl Namespace {
             name: name.to_owned(),
             entries: Vec::new(),
             subs: BTreeMap::new(),
+            externals: BTreeSet::new(),
+            locals: BTreeMap::new(),
         }
     }
 
+    /// Claim the name of a type defined in the module, so that imported
+    /// types of the same name are aliased.
+    pub fn reserve(&mut self, schema_type: &SchemaType) {
+        self.locals
+            .insert(schema_type.base_name().to_owned(), schema_type.path());
+    }
+
+    /// The name `schema_type` is known by in the module.  That is its own
+    /// name, unless another type already has it.  Then it is aliased with
+    /// the modules it is in, such as `GroupPart` for `group::part::Part`,
+    /// and a number if need be.
+    fn local_name(&mut self, schema_type: &SchemaType) -> String {
+        let path = schema_type.path();
+        let name = schema_type.base_name();
+        let mut candidates = vec![name.to_owned()];
+        let mut prefix = String::new();
+        for module in schema_type.ns.rsplit("::") {
+            let module = module.to_case(Case::Pascal);
+            if module == name || module.is_empty() {
+                continue;
+            }
+            prefix = format!("{}{}", module, prefix);
+            candidates.push(format!("{}{}", prefix, name));
+        }
+        let last = candidates.last().cloned().unwrap_or_default();
+        candidates.extend((2..).map(|count| format!("{}{}", last, count)).take(100));
+
+        for candidate in candidates {
+            match self.locals.get(&candidate) {
+                Some(existing) if *existing != path => continue,
+                _ => {
+                    self.locals.insert(candidate.clone(), path);
+                    return candidate;
+                }
+            }
+        }
+        name.to_owned()
+    }
+
+    /// Imported types that had to be aliased, with their full path and alias
+    pub fn aliases(&self) -> Vec<(String, String)> {
+        self.locals
+            .iter()
+            .filter(|(local, path)| !path.ends_with(&format!("::{}", local)) && *path != *local)
+            .map(|(local, path)| (path.to_owned(), local.to_owned()))
+            .collect()
+    }
+
     pub fn cmp(ns: &str, super_ns: &str) -> Option<Ordering> {
         if ns.is_empty() {
             return Some(Ordering::Less);
@@ -37,21 +98,33 @@ impl Namespace {
         }
     }
 
-    pub fn add_type(&mut self, schema_type: &SchemaType) -> Result<()> {
+    /// Import a type.  Returns the name the type is known by in the module,
+    /// which is an alias if the name is already taken.
+    pub fn add_type(&mut self, schema_type: &SchemaType) -> Result<String> {
+        // External types without a path, such as `u64`, need no import.
         // If everything is empty, this is probably a Rust type, such as String.
-        // Let's try just skipping altogether.
-        if self.name.is_empty() && schema_type.ns.is_empty() {
-            /*
-            if !self.entries.contains(&schema_type.name) {
-                self.entries.push(schema_type.name.clone());
-            }
-            */
-            return Ok(());
+        if schema_type.ns.is_empty() && (schema_type.external || self.name.is_empty()) {
+            return Ok(schema_type.name.clone());
+        }
+
+        let name = schema_type.base_name();
+        let local = self.local_name(schema_type);
+        let entry = match local == name {
+            true => name.to_owned(),
+            false => format!("{} as {}", name, local),
+        };
+        let generics = &schema_type.name[name.len()..];
+
+        if schema_type.external {
+            self.externals
+                .insert(format!("{}::{}", &schema_type.ns, entry));
+            return Ok(format!("{}{}", local, generics));
         }
+
         let mut ns = schema_type.ns.split("::").collect::<Vec<&str>>();
         ns.reverse();
-        self.add(&mut ns, &schema_type.name)?;
-        Ok(())
+        self.add(&mut ns, &entry)?;
+        Ok(format!("{}{}", local, generics))
     }
 
     pub fn add(&mut self, ns: &mut Vec<&str>, name: &str) -> Result<()> {
@@ -88,6 +161,11 @@ impl Namespace {
         }
     }
 
+    /// The full paths to `use` for types outside of the generated crate
+    pub fn use_externals(&self) -> Vec<String> {
+        self.externals.iter().cloned().collect()
+    }
+
     pub fn use_crates(&self, super_ns: &str, parent_ns: &str) -> String {
         let ns = match parent_ns.is_empty() {
             true => self.name.clone(),
@@ -271,6 +349,51 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_use_externals() {
+        let mut namespace = Namespace::new("");
+        namespace
+            .add_type(&SchemaType::external(
+                "chrono::DateTime<chrono::FixedOffset>",
+            ))
+            .expect("oops");
+        namespace
+            .add_type(&SchemaType::external("uuid::Uuid"))
+            .expect("oops");
+        namespace
+            .add_type(&SchemaType::external("u64"))
+            .expect("oops");
+
+        assert_eq!(
+            namespace.use_externals(),
+            vec!["chrono::DateTime".to_owned(), "uuid::Uuid".to_owned()]
+        );
+        assert_eq!(namespace.use_crates("", ""), "");
+    }
+
+    #[test]
+    fn test_aliases() {
+        let mut namespace = Namespace::new("");
+        namespace.reserve(&SchemaType::new("oscal_catalog::control::part", "Part"));
+
+        let group_part = SchemaType::new("oscal_catalog::group::part", "Part");
+        assert_eq!(namespace.add_type(&group_part).expect("oops"), "GroupPart");
+        assert_eq!(namespace.add_type(&group_part).expect("oops"), "GroupPart");
+        let metadata = SchemaType::new("oscal_metadata::metadata", "Metadata");
+        assert_eq!(namespace.add_type(&metadata).expect("oops"), "Metadata");
+
+        assert!(namespace
+            .use_crates("", "")
+            .contains("part::Part as GroupPart"));
+        assert_eq!(
+            namespace.aliases(),
+            vec![(
+                "oscal_catalog::group::part::Part".to_owned(),
+                "GroupPart".to_owned()
+            )]
+        );
+    }
+
     #[test]
     fn test_namespace_cmp() {
         let super_ns = "a::b";
@@ -0,0 +1,357 @@
+use anyhow::Result;
+use indexmap::IndexMap;
+use similar::TextDiff;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::CARGO_HEADER;
+
+/// The header line that carries the generation timestamp.  It is ignored
+/// when comparing generated content against what is on disk.
+static TIMESTAMP_PREFIX: &str = "//! This file was auto-generated at";
+
+/// The generated crate, rendered in memory.
+///
+/// File paths are relative to the output folder, such as `src/lib.rs`.
+#[derive(Debug, Default)]
+pub struct Output {
+    files: IndexMap<PathBuf, String>,
+}
+
+/// What [`Output::write`] did to the output folder.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct WriteSummary {
+    pub written: usize,
+    pub unchanged: usize,
+    pub deleted: usize,
+}
+
+impl std::fmt::Display for WriteSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} written, {} unchanged, {} deleted",
+            self.written, self.unchanged, self.deleted
+        )
+    }
+}
+
+impl Output {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, path: &Path, content: String) {
+        self.files.insert(path.to_path_buf(), content);
+    }
+
+    pub fn iter(&self) -> indexmap::map::Iter<'_, PathBuf, String> {
+        self.files.iter()
+    }
+
+    /// Write the files to the output folder, creating folders as needed.
+    /// Files whose content is unchanged are not touched, so their mtime is
+    /// preserved.
+    ///
+    /// If `prune` is set, generated files that are no longer produced, such
+    /// as types that disappeared from the schema, are deleted.
+    pub fn write(&self, root: &Path, prune: bool) -> Result<WriteSummary> {
+        let mut summary = WriteSummary::default();
+
+        for (path, content) in self.iter() {
+            let file_path = root.join(path);
+            if let Ok(existing) = fs::read(&file_path) {
+                if existing == content.as_bytes() {
+                    summary.unchanged += 1;
+                    continue;
+                }
+            }
+            if let Some(parent) = file_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(file_path, content)?;
+            summary.written += 1;
+        }
+
+        if prune {
+            for path in existing_files(root)? {
+                if self.files.contains_key(&path) {
+                    continue;
+                }
+                fs::remove_file(root.join(&path))?;
+                summary.deleted += 1;
+            }
+            remove_empty_dirs(&root.join("src"))?;
+        }
+
+        Ok(summary)
+    }
+
+    /// Compare the generated files against the output folder, without writing
+    /// anything.  A unified diff is printed for every file that was changed,
+    /// added or removed.
+    ///
+    /// Returns `true` if the output folder is up to date.
+    pub fn check(&self, root: &Path) -> Result<bool> {
+        let mut up_to_date = true;
+
+        for (path, content) in self.iter() {
+            let file_path = root.join(path);
+            let existing = match file_path.exists() {
+                true => Some(fs::read_to_string(&file_path)?),
+                false => None,
+            };
+            let old = existing.as_deref().map(normalize).unwrap_or_default();
+            let new = normalize(content);
+            if existing.is_some() && old == new {
+                continue;
+            }
+            up_to_date = false;
+            let old_name = match existing {
+                Some(_) => format!("a/{}", path.display()),
+                None => "/dev/null".to_owned(),
+            };
+            print_diff(&old, &new, &old_name, &format!("b/{}", path.display()));
+        }
+
+        for path in existing_files(root)? {
+            if self.files.contains_key(&path) {
+                continue;
+            }
+            up_to_date = false;
+            let old = normalize(&fs::read_to_string(root.join(&path))?);
+            print_diff(&old, "", &format!("a/{}", path.display()), "/dev/null");
+        }
+
+        Ok(up_to_date)
+    }
+}
+
+/// Drop the timestamp line of a generated file, which changes on every run
+/// and is only there with `--timestamp`.  Everything else, line endings
+/// included, is kept, so that a file is only up to date if
+/// [`Output::write`] would write the same, timestamp aside.
+fn normalize(content: &str) -> String {
+    content
+        .split_inclusive('\n')
+        .filter(|line| !line.starts_with(TIMESTAMP_PREFIX))
+        .collect()
+}
+
+fn print_diff(old: &str, new: &str, old_name: &str, new_name: &str) {
+    print!("{}", render_diff(old, new, old_name, new_name));
+}
+
+/// A unified diff of `old` and `new`.  An empty file added or removed has
+/// no lines to diff, so says so instead.
+fn render_diff(old: &str, new: &str, old_name: &str, new_name: &str) -> String {
+    if old.is_empty() && new.is_empty() {
+        let what = match old_name == "/dev/null" {
+            true => "added",
+            false => "removed",
+        };
+        return format!("--- {}\n+++ {}\n{} empty file\n", old_name, new_name, what);
+    }
+    let diff = TextDiff::from_lines(old, new);
+    diff.unified_diff().header(old_name, new_name).to_string()
+}
+
+/// The files in the output folder that the generator is responsible for:
+/// everything under `src`, and `Cargo.toml` if it was generated.  A
+/// hand-written `Cargo.toml`, for output without one, is left alone.  Paths
+/// are relative to `root`.
+fn existing_files(root: &Path) -> Result<BTreeSet<PathBuf>> {
+    let mut result = BTreeSet::new();
+    collect_files(root, Path::new("src"), &mut result)?;
+    let cargo = Path::new("Cargo.toml");
+    if let Ok(content) = fs::read_to_string(root.join(cargo)) {
+        if content.starts_with(CARGO_HEADER) {
+            result.insert(cargo.to_path_buf());
+        }
+    }
+    Ok(result)
+}
+
+fn collect_files(root: &Path, dir: &Path, result: &mut BTreeSet<PathBuf>) -> Result<()> {
+    let full_dir = root.join(dir);
+    if !full_dir.is_dir() {
+        return Ok(());
+    }
+    for entry in fs::read_dir(full_dir)? {
+        let entry = entry?;
+        let path = dir.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            collect_files(root, &path, result)?;
+        } else {
+            result.insert(path);
+        }
+    }
+    Ok(())
+}
+
+/// Remove the folders under `dir` that no longer contain any files.
+/// Returns `true` if `dir` itself is empty afterwards.
+fn remove_empty_dirs(dir: &Path) -> Result<bool> {
+    if !dir.is_dir() {
+        return Ok(false);
+    }
+    let mut is_empty = true;
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() && remove_empty_dirs(&entry.path())? {
+            fs::remove_dir(entry.path())?;
+        } else {
+            is_empty = false;
+        }
+    }
+    Ok(is_empty)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_ignores_timestamp() {
+        let old = "//! OSCAL Lib\n//! This file was auto-generated at Mon, 1 Apr 2024\npub mod a;";
+        let new = "//! OSCAL Lib\n//! This file was auto-generated at Tue, 2 Apr 2024\npub mod a;";
+        assert_eq!(normalize(old), normalize(new));
+        // Generated with --timestamp, checked without
+        assert_eq!(normalize(old), normalize("//! OSCAL Lib\npub mod a;"));
+        assert_ne!(normalize(old), normalize("//! OSCAL Lib\npub mod b;"));
+        // Only the timestamp, so line endings still count
+        assert_eq!(
+            normalize(&format!("{}\n", old)),
+            format!("{}\n", normalize(new))
+        );
+        assert_ne!(normalize("pub mod a;\n"), normalize("pub mod a;"));
+        assert_ne!(normalize("pub mod a;\r\n"), normalize("pub mod a;\n"));
+    }
+
+    #[test]
+    fn test_render_diff() {
+        let diff = render_diff(
+            "pub mod a;\n",
+            "pub mod b;\n",
+            "a/src/lib.rs",
+            "b/src/lib.rs",
+        );
+        assert!(diff.contains("-pub mod a;\n+pub mod b;\n"));
+        assert!(!diff.contains("No newline at end of file"));
+        assert_eq!(
+            render_diff("", "", "/dev/null", "b/src/a/mod.rs"),
+            "--- /dev/null\n+++ b/src/a/mod.rs\nadded empty file\n"
+        );
+        assert_eq!(
+            render_diff("", "", "a/src/a/mod.rs", "/dev/null"),
+            "--- a/src/a/mod.rs\n+++ /dev/null\nremoved empty file\n"
+        );
+    }
+
+    fn output(files: &[(&str, &str)]) -> Output {
+        let mut output = Output::new();
+        for (path, content) in files {
+            output.add(Path::new(path), content.to_string());
+        }
+        output
+    }
+
+    fn summary(written: usize, unchanged: usize, deleted: usize) -> WriteSummary {
+        WriteSummary {
+            written,
+            unchanged,
+            deleted,
+        }
+    }
+
+    #[test]
+    fn test_write_if_changed() {
+        let root = tempfile::tempdir().expect("oops");
+        let root = root.path();
+
+        let first = output(&[("src/lib.rs", "pub mod a;"), ("src/a/mod.rs", "")]);
+        assert_eq!(first.write(root, false).expect("oops"), summary(2, 0, 0));
+        assert_eq!(
+            fs::read_to_string(root.join("src/lib.rs")).expect("oops"),
+            "pub mod a;"
+        );
+        assert_eq!(first.write(root, false).expect("oops"), summary(0, 2, 0));
+        assert!(first.check(root).expect("oops"));
+
+        // What write would rewrite is out of date
+        fs::write(root.join("src/lib.rs"), "pub mod a;\r\n").expect("oops");
+        assert!(!first.check(root).expect("oops"));
+        assert_eq!(first.write(root, false).expect("oops"), summary(1, 1, 0));
+
+        let second = output(&[("src/lib.rs", "pub mod b;"), ("src/a/mod.rs", "")]);
+        assert!(!second.check(root).expect("oops"));
+        assert_eq!(second.write(root, false).expect("oops"), summary(1, 1, 0));
+        assert_eq!(
+            fs::read_to_string(root.join("src/lib.rs")).expect("oops"),
+            "pub mod b;"
+        );
+        assert_eq!(
+            summary(1, 1, 0).to_string(),
+            "1 written, 1 unchanged, 0 deleted"
+        );
+    }
+
+    #[test]
+    fn test_check_ignores_timestamp() {
+        let root = tempfile::tempdir().expect("oops");
+        let root = root.path();
+
+        let stamped = output(&[(
+            "src/lib.rs",
+            "//! OSCAL Lib\n//! This file was auto-generated at Mon, 1 Apr 2024\npub mod a;\n",
+        )]);
+        let unstamped = output(&[("src/lib.rs", "//! OSCAL Lib\npub mod a;\n")]);
+        stamped.write(root, false).expect("oops");
+        assert!(unstamped.check(root).expect("oops"));
+        unstamped.write(root, false).expect("oops");
+        assert!(stamped.check(root).expect("oops"));
+
+        let changed = output(&[("src/lib.rs", "//! OSCAL Lib\npub mod b;\n")]);
+        assert!(!changed.check(root).expect("oops"));
+    }
+
+    #[test]
+    fn test_prune() {
+        let root = tempfile::tempdir().expect("oops");
+        let root = root.path();
+
+        let first = output(&[
+            ("Cargo.toml", &format!("{}\n[package]", CARGO_HEADER)),
+            ("src/lib.rs", "pub mod a;\npub mod b;"),
+            ("src/a/mod.rs", ""),
+            ("src/b/mod.rs", ""),
+            ("src/b/c/mod.rs", ""),
+        ]);
+        first.write(root, false).expect("oops");
+
+        // Without Cargo.toml, and without the b module
+        let second = output(&[("src/lib.rs", "pub mod a;"), ("src/a/mod.rs", "")]);
+        assert!(!second.check(root).expect("oops"));
+        assert_eq!(second.write(root, false).expect("oops"), summary(1, 1, 0));
+        assert!(root.join("src/b/c/mod.rs").exists());
+
+        assert_eq!(second.write(root, true).expect("oops"), summary(0, 2, 3));
+        assert!(!root.join("Cargo.toml").exists());
+        assert!(!root.join("src/b").exists());
+        assert!(root.join("src/a/mod.rs").exists());
+        assert!(second.check(root).expect("oops"));
+    }
+
+    #[test]
+    fn test_prune_keeps_hand_written_cargo() {
+        let root = tempfile::tempdir().expect("oops");
+        let root = root.path();
+        fs::write(root.join("Cargo.toml"), "[package]").expect("oops");
+
+        let output = output(&[("src/lib.rs", "")]);
+        assert_eq!(output.write(root, true).expect("oops"), summary(1, 0, 0));
+        assert!(root.join("Cargo.toml").exists());
+        assert!(output.check(root).expect("oops"));
+    }
+}
@@ -0,0 +1,358 @@
+//! Export of the [`TypeGraph`] as Graphviz DOT or JSON, for the `graph`
+//! subcommand.
+//!
+//! Nodes are the generated types, plus the leaf types their fields refer to,
+//! such as `oscal_types` datatypes.  Edges are the fields.  Types that refer
+//! to themselves, directly or not, are highlighted, as are the edges between
+//! them.
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fmt::Write;
+
+use crate::{Error, TypeEdge, TypeGraph};
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum GraphFormat {
+    Dot,
+    Json,
+}
+
+/// The part of the graph to export
+pub struct GraphExport<'a> {
+    graph: &'a TypeGraph,
+    /// Every type to export, leaves included
+    types: BTreeSet<String>,
+    /// Types in a cycle, with the index of their cycle
+    recursive: BTreeMap<String, usize>,
+}
+
+#[derive(Serialize)]
+struct JsonGraph {
+    nodes: Vec<JsonNode>,
+    edges: Vec<JsonEdge>,
+}
+
+#[derive(Serialize)]
+struct JsonNode {
+    path: String,
+    /// A type outside of the generated crate, such as `String`
+    leaf: bool,
+    recursive: bool,
+}
+
+#[derive(Serialize)]
+struct JsonEdge {
+    from: String,
+    to: String,
+    /// The JSON property name, or `None` for the inner type of a newtype
+    field: Option<String>,
+    optional: bool,
+    array: bool,
+    boxed: bool,
+    recursive: bool,
+}
+
+impl<'a> GraphExport<'a> {
+    /// The whole graph, or with a `root`, the types reachable from it.  With
+    /// `reverse`, the types the root is reachable from instead, such as
+    /// everything that refers to `Metadata`.  The root is a type name or
+    /// path.
+    pub fn new(graph: &'a TypeGraph, root: Option<&str>, reverse: bool) -> Result<Self> {
+        let recursive = graph
+            .recursive_components()
+            .into_iter()
+            .enumerate()
+            .flat_map(|(index, types)| types.into_iter().map(move |path| (path, index)))
+            .collect();
+
+        let mut types = BTreeSet::new();
+        for (path, node) in graph.iter() {
+            types.insert(path.to_owned());
+            for edge in &node.edges {
+                types.insert(edge.target.clone());
+            }
+        }
+
+        let Some(root) = root else {
+            return Ok(Self {
+                graph,
+                types,
+                recursive,
+            });
+        };
+
+        let root = match graph.get(root) {
+            Some(_) => root.to_owned(),
+            None => {
+                let mut paths: Vec<String> = graph
+                    .iter()
+                    .filter(|(_, node)| node.schema_type.name == root)
+                    .map(|(path, _)| path.to_owned())
+                    .collect();
+                match paths.len() {
+                    0 => return Err(Error::UnknownType(root.to_owned()).into()),
+                    1 => paths.remove(0),
+                    _ => {
+                        let paths = paths.join(", ");
+                        return Err(Error::AmbiguousType(root.to_owned(), paths).into());
+                    }
+                }
+            }
+        };
+
+        let mut reached = BTreeSet::from([root.clone()]);
+        let mut queue = VecDeque::from([root]);
+        while let Some(path) = queue.pop_front() {
+            let next: Vec<String> = match reverse {
+                false => graph
+                    .get(&path)
+                    .map(|node| node.edges.iter().map(|edge| edge.target.clone()).collect())
+                    .unwrap_or_default(),
+                true => graph
+                    .iter()
+                    .filter(|(_, node)| node.edges.iter().any(|edge| edge.target == path))
+                    .map(|(from, _)| from.to_owned())
+                    .collect(),
+            };
+            for path in next {
+                if reached.insert(path.clone()) {
+                    queue.push_back(path);
+                }
+            }
+        }
+
+        Ok(Self {
+            graph,
+            types: reached,
+            recursive,
+        })
+    }
+
+    pub fn render(&self, format: GraphFormat) -> Result<String> {
+        match format {
+            GraphFormat::Dot => self.to_dot(),
+            GraphFormat::Json => self.to_json(),
+        }
+    }
+
+    /// The exported edges, with the type they start from
+    fn edges(&self) -> impl Iterator<Item = (&'a String, &'a TypeEdge)> + '_ {
+        self.graph
+            .iter()
+            .filter(|(path, _)| self.types.contains(*path))
+            .flat_map(|(path, node)| node.edges.iter().map(move |edge| (path, edge)))
+            .filter(|(_, edge)| self.types.contains(&edge.target))
+    }
+
+    /// Is the edge part of a cycle, that is between types of the same one
+    fn is_recursive(&self, from: &str, edge: &TypeEdge) -> bool {
+        match (self.recursive.get(from), self.recursive.get(&edge.target)) {
+            (Some(from), Some(to)) => from == to,
+            _ => false,
+        }
+    }
+
+    fn to_dot(&self) -> Result<String> {
+        let mut buffer = String::new();
+        writeln!(buffer, "digraph types {{")?;
+        writeln!(buffer, "    rankdir=LR;")?;
+        writeln!(buffer, "    node [shape=box];")?;
+
+        for path in &self.types {
+            let mut attributes = vec![format!("label={}", dot_string(path))];
+            if self.graph.get(path).is_none() {
+                attributes.push("shape=ellipse".to_owned());
+            }
+            if self.recursive.contains_key(path) {
+                attributes.push("color=red".to_owned());
+            }
+            writeln!(
+                buffer,
+                "    {} [{}];",
+                dot_string(path),
+                attributes.join(", ")
+            )?;
+        }
+
+        for (from, edge) in self.edges() {
+            let mut attributes = vec![format!("label={}", dot_string(&edge_label(edge)))];
+            if edge.optional {
+                attributes.push("style=dashed".to_owned());
+            }
+            if self.is_recursive(from, edge) {
+                attributes.push("color=red".to_owned());
+            }
+            writeln!(
+                buffer,
+                "    {} -> {} [{}];",
+                dot_string(from),
+                dot_string(&edge.target),
+                attributes.join(", ")
+            )?;
+        }
+
+        writeln!(buffer, "}}")?;
+        Ok(buffer)
+    }
+
+    fn to_json(&self) -> Result<String> {
+        let nodes = self
+            .types
+            .iter()
+            .map(|path| JsonNode {
+                path: path.to_owned(),
+                leaf: self.graph.get(path).is_none(),
+                recursive: self.recursive.contains_key(path),
+            })
+            .collect();
+        let edges = self
+            .edges()
+            .map(|(from, edge)| JsonEdge {
+                from: from.to_owned(),
+                to: edge.target.clone(),
+                field: edge.json_name.clone(),
+                optional: edge.optional,
+                array: edge.array,
+                boxed: edge.boxed,
+                recursive: self.is_recursive(from, edge),
+            })
+            .collect();
+        Ok(serde_json::to_string_pretty(&JsonGraph { nodes, edges })?)
+    }
+}
+
+/// The JSON property name, marked `[]` for arrays, `?` if optional and `Box`
+/// if boxed.  Newtypes are labelled `newtype`.
+fn edge_label(edge: &TypeEdge) -> String {
+    let Some(json_name) = &edge.json_name else {
+        return "newtype".to_owned();
+    };
+    let mut label = json_name.to_owned();
+    if edge.array {
+        label.push_str("[]");
+    }
+    if edge.optional {
+        label.push('?');
+    }
+    if edge.boxed {
+        label.push_str(" (Box)");
+    }
+    label
+}
+
+fn dot_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Property, Resolver, SchemaObject, SchemaType};
+
+    fn graph() -> TypeGraph {
+        let mut resolver = Resolver::new();
+        resolver.add_type("#metadata", SchemaType::new("metadata", "Metadata"));
+        resolver.add_type("#catalog", SchemaType::new("catalog", "Catalog"));
+        resolver.add_type("#token", SchemaType::new("oscal_types", "TokenDatatype"));
+
+        let mut graph = TypeGraph::default();
+        for (ns, name, type_ref) in [
+            ("catalog", "Catalog", "#metadata"),
+            ("metadata", "Metadata", "#token"),
+            ("group", "Group", "#catalog"),
+        ] {
+            let obj = SchemaObject {
+                type_ref: Some(type_ref.to_owned()),
+                ..SchemaObject::test_object(ns, name)
+            };
+            graph.add_object(&obj, &resolver);
+        }
+        graph
+    }
+
+    #[test]
+    fn test_reachable() {
+        let graph = graph();
+        let export = GraphExport::new(&graph, Some("Catalog"), false).expect("oops");
+        assert_eq!(
+            export.types.iter().collect::<Vec<_>>(),
+            vec![
+                "catalog::Catalog",
+                "metadata::Metadata",
+                "oscal_types::TokenDatatype"
+            ]
+        );
+
+        let export = GraphExport::new(&graph, Some("metadata::Metadata"), true).expect("oops");
+        assert_eq!(
+            export.types.iter().collect::<Vec<_>>(),
+            vec!["catalog::Catalog", "group::Group", "metadata::Metadata"]
+        );
+
+        assert!(GraphExport::new(&graph, Some("Nope"), false).is_err());
+    }
+
+    #[test]
+    fn test_ambiguous_root() {
+        let mut graph = graph();
+        let resolver = Resolver::new();
+        graph.add_object(&SchemaObject::test_object("other", "Metadata"), &resolver);
+
+        let error = GraphExport::new(&graph, Some("Metadata"), false)
+            .err()
+            .expect("oops");
+        assert_eq!(
+            error.to_string(),
+            "Type name Metadata is ambiguous, give one of: metadata::Metadata, other::Metadata"
+        );
+        assert!(GraphExport::new(&graph, Some("other::Metadata"), false).is_ok());
+    }
+
+    #[test]
+    fn test_recursive_edges() {
+        let mut resolver = Resolver::new();
+        for name in ["a", "b", "c"] {
+            resolver.add_type(&format!("#{}", name), SchemaType::new(name, name));
+        }
+        // Labelled by JSON name, not by field name
+        let prop = |name: &str| {
+            let field = format!("to_{}", name);
+            let prop = Property {
+                array: true,
+                ..Property::test_property(&field, &format!("to-{}", name), &format!("#{}", name))
+            };
+            (field, prop)
+        };
+        // a and b are a cycle, c is one on its own, and a refers to c
+        let mut graph = TypeGraph::default();
+        for (name, props) in [("a", vec!["b", "c"]), ("b", vec!["a"]), ("c", vec!["c"])] {
+            let obj = SchemaObject {
+                props: Some(props.into_iter().map(prop).collect()),
+                ..SchemaObject::test_object(name, name)
+            };
+            graph.add_object(&obj, &resolver);
+        }
+
+        let export = GraphExport::new(&graph, None, false).expect("oops");
+        let dot = export.render(GraphFormat::Dot).expect("oops");
+        assert!(dot.contains(r#""a::a" -> "b::b" [label="to-b[]?", style=dashed, color=red];"#));
+        assert!(dot.contains(r#""c::c" -> "c::c" [label="to-c[]?", style=dashed, color=red];"#));
+        assert!(dot.contains(r#""a::a" -> "c::c" [label="to-c[]?", style=dashed];"#));
+        let json = export.render(GraphFormat::Json).expect("oops");
+        assert!(json.contains(r#""field": "to-b""#));
+    }
+
+    #[test]
+    fn test_dot() {
+        let graph = graph();
+        let export = GraphExport::new(&graph, Some("Group"), false).expect("oops");
+        let dot = export.render(GraphFormat::Dot).expect("oops");
+        assert!(dot.starts_with("digraph types {"));
+        assert!(dot.contains(r#""group::Group" -> "catalog::Catalog" [label="newtype"];"#));
+        assert!(dot.contains(
+            r#""oscal_types::TokenDatatype" [label="oscal_types::TokenDatatype", shape=ellipse];"#
+        ));
+    }
+}
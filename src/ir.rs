@@ -0,0 +1,77 @@
+//! The intermediate representation: the parsed [`Schema`] tree and the
+//! [`Resolver`] map, which is everything generation needs.
+//!
+//! The IR is written as JSON by `--dump-ir` and read back by `--from-ir`, so
+//! that the parser output can be inspected, patched by hand, or used for
+//! golden tests.  Tree entries keep the schema order and the resolver map is
+//! sorted, so the same schema always dumps the same JSON.
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use crate::{Resolver, Schema};
+
+#[derive(Serialize, Deserialize)]
+pub struct Ir {
+    pub schema: Schema,
+    pub resolver: Resolver,
+}
+
+impl Ir {
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)? + "\n")
+    }
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        fs::write(path, self.to_json()?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Property, SchemaObject, SchemaType, TypeRef};
+    use indexmap::IndexMap;
+
+    #[test]
+    fn test_round_trip() {
+        let mut resolver = Resolver::new();
+        resolver.add_type("#metadata", SchemaType::new("metadata", "Metadata"));
+
+        let title = Property {
+            optional: false,
+            title: Some("Title".to_owned()),
+            ..Property::test_property("title", "title", "#/definitions/StringDatatype")
+        };
+        let obj = SchemaObject {
+            id: Some("#metadata".to_owned()),
+            description: Some("Provides information about the containing document".to_owned()),
+            _type: Some("object".to_owned()),
+            props: Some(IndexMap::from([("title".to_owned(), title)])),
+            ..SchemaObject::test_object("metadata", "Metadata")
+        };
+        let mut schema = Schema::new("http://json-schema.org/draft-07/schema#", "1.1.2", "");
+        schema.add_tree("metadata").expect("oops");
+        schema
+            .get_tree_mut("metadata")
+            .expect("oops")
+            .add_object("metadata", &obj)
+            .expect("oops");
+
+        let json = Ir { schema, resolver }.to_json().expect("oops");
+        let ir = Ir::from_json(&json).expect("oops");
+        assert_eq!(ir.to_json().expect("oops"), json);
+        assert_eq!(ir.schema.objects(), vec![&obj]);
+        assert_eq!(
+            ir.resolver
+                .resolve(&TypeRef::from_ref(Some("#metadata".to_owned()))),
+            Some(SchemaType::new("metadata", "Metadata"))
+        );
+    }
+}
@@ -1,73 +1,389 @@
 use anyhow::Result;
-use clap::Parser;
+use clap::{Args, Parser, Subcommand};
 use serde_json::Value;
-use std::fs::{self, remove_dir_all};
-use std::path::PathBuf;
+use std::fs;
+use std::path::{Path, PathBuf};
 
 use any_of::*;
+use config::*;
+use coverage::*;
 use data_type::*;
+use dedup::*;
+use diagnostics::*;
 use error::Error;
 use generate::*;
+use graph_export::*;
+use ir::*;
 use parse::*;
 use property::*;
+use resolution::*;
 use resolver::*;
 use schema::*;
+use schema_diff::*;
 use schema_object::*;
 use schema_type::*;
+use shape::*;
 use string_type::*;
 use tree_entry::*;
+use type_graph::*;
 use type_ref::*;
 use util::*;
+use versions::*;
 
 mod any_of;
+mod config;
+mod coverage;
 mod data_type;
+mod dedup;
+mod diagnostics;
 mod error;
 mod generate;
+mod graph_export;
+mod ir;
 mod parse;
 mod property;
+mod resolution;
 mod resolver;
 mod schema;
+mod schema_diff;
 mod schema_object;
 mod schema_type;
+mod shape;
 mod string_type;
 mod tree_entry;
+mod type_graph;
 mod type_ref;
 mod util;
+mod versions;
 
 #[derive(Parser)]
-#[command(version, about, long_about = None)]
+#[command(
+    version,
+    about,
+    long_about = None,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
 struct Cli {
-    /// The input schema file
-    #[arg(short, long, value_name = "FILE")]
-    schema: PathBuf,
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// The input schema file.  Given more than once, such as for OSCAL
+    /// 1.0.4 and 1.1.2, each version gets a module, such as `v1_0_4`, oldest
+    /// first.  Types that didn't change are re-exported from the oldest
+    /// version they are in.
+    #[arg(
+        short,
+        long,
+        value_name = "FILE",
+        required_unless_present = "from_ir",
+        conflicts_with = "from_ir"
+    )]
+    schema: Vec<PathBuf>,
+    /// Generate from an intermediate representation written by `--dump-ir`,
+    /// rather than from a schema.  The parsing options in the config, such
+    /// as `[identifiers]` and `[types]`, were applied when it was dumped.
+    #[arg(long, value_name = "FILE")]
+    from_ir: Option<PathBuf>,
+    /// Also write the parsed tree and resolver map to this file, as JSON
+    #[arg(long, value_name = "FILE")]
+    dump_ir: Option<PathBuf>,
     /// Where to put the output
     #[arg(short, long, value_name = "DIR", default_value = "./output")]
     output: PathBuf,
-    /// Delete output folder first
+    /// Delete generated files that are no longer produced, such as types that
+    /// disappeared from the schema.  Only files under `src`, and a generated
+    /// `Cargo.toml`, are ever deleted.
+    #[arg(long)]
+    prune: bool,
+    /// Verify that the output folder is up to date, without writing anything.
+    /// Differences are printed as a unified diff.
+    #[arg(long, conflicts_with = "prune")]
+    check: bool,
+    /// Record the generation time in lib.rs.  By default the output only
+    /// depends on the inputs and the generator version.
     #[arg(long)]
-    remove: bool,
+    timestamp: bool,
     /// Where to get the static content
     #[arg(short, long, value_name = "DIR", default_value = "./static")]
     input: PathBuf,
+    /// Fail on schema warnings and name collisions, rather than warning and
+    /// going on.  Same as `strict = true` in the config.
+    #[arg(long, global = true)]
+    strict: bool,
+    /// How schema and generation diagnostics are printed, to stderr
+    #[arg(long, global = true, value_enum, default_value_t = DiagnosticsFormat::Text)]
+    diagnostics_format: DiagnosticsFormat,
+    /// Generate structurally identical types once, with aliases at the other
+    /// paths.  Same as `dedup = true` in the config.
+    #[arg(long)]
+    dedup: bool,
+    /// Generator config.  Either a codegen.toml, or a Cargo.toml with a
+    /// [package.metadata.oscal_codegen] section.  Defaults to ./codegen.toml,
+    /// if it exists.
+    #[arg(short, long, value_name = "FILE")]
+    config: Option<PathBuf>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Export the type graph, as Graphviz DOT or JSON, rather than generating
+    Graph(GraphArgs),
+    /// Report what changed between two schemas, such as two OSCAL releases,
+    /// and whether it breaks the generated crate
+    Diff(DiffArgs),
+    /// Report which schema keywords the generated code honors, partially
+    /// honors or drops, and where
+    Coverage(CoverageArgs),
+}
+
+#[derive(Args)]
+struct GraphArgs {
+    /// The input schema file
+    #[arg(short, long, value_name = "FILE")]
+    schema: PathBuf,
+    /// Generator config, as for generating
+    #[arg(short, long, value_name = "FILE")]
+    config: Option<PathBuf>,
+    /// Only export the types reachable from this one, given by name or path
+    #[arg(short, long, value_name = "TYPE")]
+    root: Option<String>,
+    /// Export the types the root is reachable from instead
+    #[arg(long, requires = "root")]
+    reverse: bool,
+    #[arg(short, long, value_enum, default_value_t = GraphFormat::Dot)]
+    format: GraphFormat,
+    /// Where to write the graph.  Defaults to stdout.
+    #[arg(short, long, value_name = "FILE")]
+    output: Option<PathBuf>,
+}
+
+#[derive(Args)]
+struct DiffArgs {
+    /// The schema the crate was generated from
+    #[arg(value_name = "OLD")]
+    old: PathBuf,
+    /// The schema to compare it to
+    #[arg(value_name = "NEW")]
+    new: PathBuf,
+    /// Generator config, as for generating
+    #[arg(short, long, value_name = "FILE")]
+    config: Option<PathBuf>,
+    #[arg(short, long, value_enum, default_value_t = DiffFormat::Text)]
+    format: DiffFormat,
+    /// Where to write the report.  Defaults to stdout.
+    #[arg(short, long, value_name = "FILE")]
+    output: Option<PathBuf>,
+    /// Exit with an error status if any change is breaking
+    #[arg(long)]
+    fail_on_breaking: bool,
+}
+
+#[derive(Args)]
+struct CoverageArgs {
+    /// The input schema file
+    #[arg(short, long, value_name = "FILE")]
+    schema: PathBuf,
+    /// Generator config, as for generating
+    #[arg(short, long, value_name = "FILE")]
+    config: Option<PathBuf>,
+    #[arg(short, long, value_enum, default_value_t = CoverageFormat::Text)]
+    format: CoverageFormat,
+    /// How many of the worst locations to list
+    #[arg(long, value_name = "N", default_value_t = 20)]
+    top: usize,
+    /// Where to write the report.  Defaults to stdout.
+    #[arg(short, long, value_name = "FILE")]
+    output: Option<PathBuf>,
 }
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
+    let format = cli.diagnostics_format;
+    match cli.command {
+        Some(Command::Graph(args)) => return graph(args, format, cli.strict),
+        Some(Command::Diff(args)) => return diff(args, format, cli.strict),
+        Some(Command::Coverage(args)) => return coverage(args, format, cli.strict),
+        None => {}
+    }
+
+    let mut config = Config::load_or_default(cli.config.as_deref())?;
+    config.strict |= cli.strict;
+    config.dedup |= cli.dedup;
+
+    // Report what was found even if loading failed, since the failure is
+    // one of the diagnostics
+    let mut diagnostics = Diagnostics::new();
+    let loaded = match &cli.from_ir {
+        Some(ir_path) => {
+            load_ir(ir_path, &mut diagnostics).map(|(ir, ir_file)| (ir, vec![ir_file]))
+        }
+        None => load_schemas(&cli.schema, &config, &mut diagnostics),
+    };
+    diagnostics.report(format, config.strict)?;
+    let (ir, input_files) = loaded?;
+    if let Some(ir_path) = &cli.dump_ir {
+        ir.write(ir_path)?;
+    }
+
+    // Everything the output depends on, so that the fingerprint changes
+    // whenever the output may
+    let config_json = serde_json::to_string(&config)?;
+    let static_files = read_static(&cli.input)?;
+    let mut inputs: Vec<&[u8]> = input_files.iter().map(|file| file.as_bytes()).collect();
+    inputs.push(config_json.as_bytes());
+    for (file, content) in &static_files {
+        inputs.push(file.as_bytes());
+        inputs.push(content.as_bytes());
+    }
+    let provenance = Provenance {
+        fingerprint: fingerprint(&inputs),
+        timestamp: cli.timestamp,
+    };
+    let mut diagnostics = Diagnostics::new();
+    let output = generate(
+        &cli.input,
+        &ir.schema,
+        &ir.resolver,
+        &provenance,
+        &config,
+        &mut diagnostics,
+    )?;
+    diagnostics.report(format, config.strict)?;
 
-    if cli.remove {
-        // Don't worry about the result.  It will throw an error
-        // if the folder doesn't exist.
-        let _ = remove_dir_all(&cli.output);
+    if cli.check {
+        if !output.check(&cli.output)? {
+            eprintln!("{} is out of date", cli.output.display());
+            std::process::exit(1);
+        }
+        return Ok(());
     }
 
-    let mut resolver = Resolver::new();
-    let json_path = cli.schema;
+    let summary = output.write(&cli.output, cli.prune)?;
+    println!("{}: {}", cli.output.display(), summary);
+
+    Ok(())
+}
+
+/// Parse the schema file, returning the IR and the file content.  Problems
+/// that don't stop parsing go to `diagnostics`.
+fn load_schema(
+    json_path: &Path,
+    config: &Config,
+    diagnostics: &mut Diagnostics,
+) -> Result<(Ir, String)> {
+    let mut resolver = Resolver::with_overrides(&config.types);
     let schema_file = fs::read_to_string(json_path)?;
     let json = serde_json::from_str::<Value>(&schema_file)?;
 
-    let schema = parse_schema(&json, &mut resolver)?;
+    diagnostics.set_file(json_path);
+    let schema = parse_schema(&json, &mut resolver, &config.identifiers, diagnostics)?;
+    // Located at the definitions, where the definition wasn't found
+    let definitions_pointer = pointer("", "definitions");
+    for key in resolver.unused_overrides() {
+        let message = format!("Type override did not match any definition: {}", key);
+        diagnostics.warn(Code::UnusedOverride, &definitions_pointer, &message);
+    }
+    Ok((Ir { schema, resolver }, schema_file))
+}
+
+/// Parse the schema files, returning the IR and the file contents.
+/// Several schemas are merged into one IR, with a module per version.
+/// Dangling references go to `diagnostics`, located in their own schema.
+fn load_schemas(
+    json_paths: &[PathBuf],
+    config: &Config,
+    diagnostics: &mut Diagnostics,
+) -> Result<(Ir, Vec<String>)> {
+    let mut irs = vec![];
+    let mut schema_files = vec![];
+    for json_path in json_paths {
+        let (ir, schema_file) = load_schema(json_path, config, diagnostics)?;
+        check_refs(&ir.schema, &ir.resolver, diagnostics);
+        irs.push(ir);
+        schema_files.push(schema_file);
+    }
+    let ir = match irs.len() {
+        1 => irs.remove(0),
+        _ => merge_versions(irs)?,
+    };
+    Ok((ir, schema_files))
+}
+
+/// Read an IR file, returning the IR and the file content.  Dangling
+/// references go to `diagnostics`.
+fn load_ir(ir_path: &Path, diagnostics: &mut Diagnostics) -> Result<(Ir, String)> {
+    let ir_file = fs::read_to_string(ir_path)?;
+    let ir = Ir::from_json(&ir_file)?;
+    check_refs(&ir.schema, &ir.resolver, diagnostics);
+    Ok((ir, ir_file))
+}
+
+fn graph(args: GraphArgs, format: DiagnosticsFormat, strict: bool) -> Result<()> {
+    let mut config = Config::load_or_default(args.config.as_deref())?;
+    config.strict |= strict;
+    let mut diagnostics = Diagnostics::new();
+    let loaded = load_schema(&args.schema, &config, &mut diagnostics);
+    diagnostics.report(format, config.strict)?;
+    let (ir, _) = loaded?;
 
-    generate(cli.output, cli.input, &schema, &resolver)?;
+    // Break cycles first, so that the export shows which fields get boxed
+    let mut graph = TypeGraph::build(&ir.schema, &ir.resolver);
+    graph.break_cycles()?;
 
+    let export = GraphExport::new(&graph, args.root.as_deref(), args.reverse)?;
+    let rendered = export.render(args.format)?;
+    match args.output {
+        Some(path) => fs::write(path, rendered)?,
+        None => print!("{}", rendered),
+    }
+    Ok(())
+}
+
+fn diff(args: DiffArgs, format: DiagnosticsFormat, strict: bool) -> Result<()> {
+    let mut config = Config::load_or_default(args.config.as_deref())?;
+    config.strict |= strict;
+    let mut diagnostics = Diagnostics::new();
+    let old = load_schema(&args.old, &config, &mut diagnostics);
+    let new = load_schema(&args.new, &config, &mut diagnostics);
+    diagnostics.report(format, config.strict)?;
+    let ((old, _), (new, _)) = (old?, new?);
+
+    let diff = SchemaDiff::new(&old, &new);
+    let rendered = diff.render(args.format)?;
+    match args.output {
+        Some(path) => fs::write(path, rendered)?,
+        None => print!("{}", rendered),
+    }
+
+    if args.fail_on_breaking && diff.is_breaking() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+fn coverage(args: CoverageArgs, format: DiagnosticsFormat, strict: bool) -> Result<()> {
+    let mut config = Config::load_or_default(args.config.as_deref())?;
+    config.strict |= strict;
+    // What the parser reports is part of the coverage, rather than a failure,
+    // unless loading failed or in strict mode
+    let mut diagnostics = Diagnostics::new();
+    let (ir, schema_file) = match load_schema(&args.schema, &config, &mut diagnostics) {
+        Ok(loaded) => loaded,
+        Err(error) => {
+            diagnostics.report(format, config.strict)?;
+            return Err(error);
+        }
+    };
+    let json = serde_json::from_str::<Value>(&schema_file)?;
+
+    let coverage = Coverage::new(&json, &ir.schema.version, &diagnostics, &config.types);
+    let rendered = coverage.render(args.format, args.top)?;
+    match args.output {
+        Some(path) => fs::write(path, rendered)?,
+        None => print!("{}", rendered),
+    }
+    if config.strict {
+        diagnostics.report(format, true)?;
+    }
     Ok(())
 }
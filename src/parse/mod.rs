@@ -1,5 +1,6 @@
 use get_any_of::*;
 use get_required::*;
+use module_name::*;
 use parse_array::*;
 use parse_object::*;
 use parse_props::*;
@@ -7,7 +8,17 @@ pub use parse_schema::*;
 
 mod get_any_of;
 mod get_required;
+mod module_name;
 mod parse_array;
 mod parse_object;
 mod parse_props;
 mod parse_schema;
+
+use crate::{Diagnostics, Identifiers, Resolver};
+
+/// What the parse functions share
+pub struct ParseContext<'a> {
+    pub resolver: &'a mut Resolver,
+    pub idents: &'a Identifiers,
+    pub diagnostics: &'a mut Diagnostics,
+}
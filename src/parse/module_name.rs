@@ -0,0 +1,29 @@
+use crate::{Identifiers, Schema};
+
+/// The module name for a new object in `tree`, whose module path is `ns`.
+/// That is the configured rename, if any, made unique within the tree.
+/// See [`Schema::unique_name`].
+pub fn module_name(
+    tree: &mut Schema,
+    ns: &str,
+    name: &str,
+    qualifier: &str,
+    idents: &Identifiers,
+    pointer: &str,
+) -> String {
+    let path = match ns.is_empty() {
+        true => name.to_owned(),
+        false => format!("{}::{}", ns, name),
+    };
+    let name = match idents.renames.get(&path) {
+        Some(renamed) => idents.module(renamed),
+        None => name.to_owned(),
+    };
+    tree.unique_name(ns, &name, qualifier, pointer)
+}
+
+/// The name of the module an object's inline objects are qualified with, in
+/// case of a collision.  That is the last part of its module path.
+pub fn parent_module(ns: &str) -> &str {
+    ns.rsplit("::").next().unwrap_or(ns)
+}
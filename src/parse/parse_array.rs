@@ -2,52 +2,77 @@ use anyhow::Result;
 use convert_case::Casing;
 use serde_json::{Map, Value};
 
-use crate::{util::*, AnyOf, Error, Resolver, Schema, SchemaObject, SchemaType, TypeRef};
+use crate::{util::*, AnyOf, Code, Schema, SchemaObject, SchemaType, TypeRef};
 
-use super::{get_any_of, parse_object};
+use super::{get_any_of, module_name, parent_module, parse_object, ParseContext};
 
+/// Parse the array `items` at `items_pointer` in the schema.  Items that
+/// can't be parsed are reported, and give neither a type nor an `anyOf`.
 pub fn parse_array(
     entry_name: &str,
     items: &Map<String, Value>,
     parent_obj: &SchemaObject,
     parent_tree: &mut Schema,
-    resolver: &mut Resolver,
+    ctx: &mut ParseContext,
+    items_pointer: &str,
 ) -> Result<(Option<TypeRef>, Option<AnyOf>)> {
     // Simple case: items has a `$ref`
     if let Ok(_ref) = try_string_entry("$ref", items) {
         return Ok((Some(TypeRef::from_ref(Some(_ref.to_owned()))), None));
     }
     // Second simplest type - an AnyOf
-    else if let Some(any_of) = get_any_of(items)? {
+    let Some(any_of) = ctx.diagnostics.check(get_any_of(items), items_pointer) else {
+        return Ok((None, None));
+    };
+    if let Some(any_of) = any_of {
         return Ok((None, Some(any_of)));
     }
 
     // If there's not a `$ref`, then there needs to be a `type`, and it
     // needs to be an object
     let Ok(_type) = try_string_entry("type", items) else {
-        println!("Bad Array!");
-        println!("{:?}", &items);
-        return Err(Error::MalformedArray.into());
+        ctx.diagnostics.error(
+            Code::MalformedArray,
+            items_pointer,
+            "Array items need a $ref, anyOf or type",
+        );
+        return Ok((None, None));
     };
 
     if _type == "object" {
+        // The item title names a single item already.  Without one, use the
+        // singular of the array property name.
         let entry_name = match try_string_entry("title", items) {
-            Ok(title) => title.as_str().unplural().to_case(convert_case::Case::Snake),
-            // If there is no title, remove the 's' from the array property name
-            Err(_) => entry_name.unplural(),
+            Ok(title) => title.to_case(convert_case::Case::Snake),
+            Err(_) => ctx.idents.singular(entry_name),
+        };
+        let entry_name = module_name(
+            parent_tree,
+            &parent_obj.ns,
+            &ctx.idents.module(&entry_name),
+            parent_module(&parent_obj.ns),
+            ctx.idents,
+            items_pointer,
+        );
+        let added = parent_tree.get_or_add_tree(&entry_name);
+        let Some(sub_tree) = ctx.diagnostics.check(added, items_pointer) else {
+            return Ok((None, None));
         };
-        let sub_tree = parent_tree.get_or_add_tree(&entry_name)?;
         let ns = format!("{}::{}", &parent_obj.ns, &entry_name);
-        let name = entry_name.to_case(convert_case::Case::Pascal);
-        parse_object(&entry_name, &ns, &name, items, sub_tree, resolver)?;
+        let name = ctx
+            .idents
+            .type_name(&entry_name.to_case(convert_case::Case::Pascal));
+        parse_object(&entry_name, &ns, &name, items, sub_tree, ctx, items_pointer)?;
         return Ok((Some(TypeRef::from_type(&SchemaType::new(&ns, &name))), None));
     } else if _type == "string" {
         return Ok((Some(TypeRef::native_string()), None));
     } else {
-        println!("Unexpected type in array: {}", _type);
+        ctx.diagnostics.warn(
+            Code::UnsupportedArrayItems,
+            items_pointer,
+            &format!("Array items of type {} are not supported, left out", _type),
+        );
     }
 
-    // This is an array item with an embedded type.  Create the new type
-
     Ok((None, None))
 }
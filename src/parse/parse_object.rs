@@ -1,27 +1,37 @@
 use anyhow::Result;
 use serde_json::{Map, Value};
 
-use crate::{Resolver, Schema, SchemaObject, SchemaType};
+use crate::{Schema, SchemaObject, SchemaType};
 
-use super::parse_props;
+use super::{parse_props, ParseContext};
 
+/// Parse the object at `pointer` in the schema.  If it can't be parsed,
+/// it is reported and left out.
 pub fn parse_object(
     entry_name: &str,
     ns: &str,
     name: &str,
     map: &Map<String, Value>,
     tree: &mut Schema,
-    resolver: &mut Resolver,
+    ctx: &mut ParseContext,
+    pointer: &str,
 ) -> Result<()> {
-    let mut obj = SchemaObject::parse(ns, name, map)?;
+    let Some(mut obj) = ctx
+        .diagnostics
+        .check(SchemaObject::parse(ns, name, map), pointer)
+    else {
+        return Ok(());
+    };
+    obj.pointer = Some(pointer.to_owned());
 
     if let Some(id) = &obj.id {
         // This object has an ID.  Add it to the resolver
         let schema_type = SchemaType::from(&obj);
-        resolver.add_type(id, schema_type);
+        ctx.resolver.add_type(id, schema_type);
     }
-    parse_props(map, &mut obj, tree, resolver)?;
-    tree.add_object(entry_name, &obj)?;
+    parse_props(map, &mut obj, tree, ctx, pointer)?;
+    ctx.diagnostics
+        .check(tree.add_object(entry_name, &obj), pointer);
 
     Ok(())
 }
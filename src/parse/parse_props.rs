@@ -3,28 +3,57 @@ use convert_case::Casing;
 use indexmap::IndexMap;
 use serde_json::{Map, Value};
 
-use crate::{util::*, Property, Resolver, Schema, SchemaObject, SchemaType, TypeRef};
+use crate::{pointer, util::*, Code, Property, Schema, SchemaObject, SchemaType, TypeRef};
 
-use super::{get_any_of, get_required, parse_array, parse_object};
+use super::{
+    get_any_of, get_required, module_name, parent_module, parse_array, parse_object, ParseContext,
+};
 
+/// Parse the properties of the object at `obj_pointer` in the schema
 pub fn parse_props(
     obj_map: &Map<String, Value>,
     parent_obj: &mut SchemaObject,
     parent_tree: &mut Schema,
-    resolver: &mut Resolver,
+    ctx: &mut ParseContext,
+    obj_pointer: &str,
 ) -> Result<()> {
     let Ok(props_value) = try_map_entry("properties", obj_map) else {
         return Ok(());
     };
     let mut props: IndexMap<String, Property> = IndexMap::new();
-    let requireds = get_required(obj_map)?;
+    let required_pointer = pointer(obj_pointer, "required");
+    let Some(requireds) = ctx
+        .diagnostics
+        .check(get_required(obj_map), &required_pointer)
+    else {
+        return Ok(());
+    };
+    let props_pointer = pointer(obj_pointer, "properties");
 
     for (prop_name, prop_value) in props_value {
         let required = requireds.contains(prop_name);
+        let prop_pointer = pointer(&props_pointer, prop_name);
 
-        let entry_name = prop_name.to_case(convert_case::Case::Snake);
+        let field = ctx.idents.field(prop_name);
+        let entry_name = unique_field(&props, &field);
+        if entry_name != field {
+            let message = format!(
+                "`{}` collides with another field, renamed to `{}`",
+                field, entry_name
+            );
+            ctx.diagnostics
+                .warn(Code::NameCollision, &prop_pointer, &message);
+        }
 
-        let prop_map = value_to_map(prop_value)?;
+        let Some(prop_map) = ctx
+            .diagnostics
+            .check(value_to_map(prop_value), &prop_pointer)
+        else {
+            continue;
+        };
+        let Some(any_of) = ctx.diagnostics.check(get_any_of(prop_map), &prop_pointer) else {
+            continue;
+        };
 
         let _type = try_string_entry("type", prop_map)
             .ok()
@@ -45,6 +74,7 @@ pub fn parse_props(
                 optional: !required,
                 array: false,
                 name: entry_name.clone(),
+                json_name: prop_name.clone(),
                 title,
                 description,
                 type_ref: TypeRef::from_ref(ref_name),
@@ -53,11 +83,12 @@ pub fn parse_props(
             props.insert(entry_name, prop);
 
             // If this has anyOf, then it's a $ref with an enum
-        } else if let Some(any_of) = get_any_of(prop_map)? {
+        } else if let Some(any_of) = any_of {
             let prop = Property {
                 optional: !required,
                 array: false,
                 name: entry_name.clone(),
+                json_name: prop_name.clone(),
                 title,
                 description,
                 type_ref: TypeRef {
@@ -68,31 +99,49 @@ pub fn parse_props(
             };
             props.insert(entry_name, prop);
         } else if _type == Some("string".to_owned()) {
+            // Enum values aren't enforced, but are recorded as for `anyOf`,
+            // such as for `diff`
+            let enums = try_array_entry("enum", prop_map).ok().map(|values| {
+                values
+                    .iter()
+                    .filter_map(|value| value_to_string(value).ok().cloned())
+                    .collect()
+            });
             let prop = Property {
                 optional: !required,
                 array: false,
                 name: entry_name.clone(),
+                json_name: prop_name.clone(),
                 title,
                 description,
                 type_ref: TypeRef::native_string(),
-                enums: None,
+                enums,
             };
             props.insert(entry_name, prop);
 
         // The next easy type is if the prop is an array
         } else if _type == Some("array".to_owned()) {
             // All arrays have an 'items' element
-            let items = try_map_entry("items", prop_map)?;
-            let (t_opt, any_opt) =
-                parse_array(&entry_name, items, parent_obj, parent_tree, resolver)?;
-            if t_opt.is_none() && any_opt.is_none() {
-                println!("Weird: {}, {:?}", &prop_name, &parent_obj.name);
-            }
+            let Some(items) = ctx
+                .diagnostics
+                .check(try_map_entry("items", prop_map), &prop_pointer)
+            else {
+                continue;
+            };
+            let (t_opt, any_opt) = parse_array(
+                unraw(&entry_name),
+                items,
+                parent_obj,
+                parent_tree,
+                ctx,
+                &pointer(&prop_pointer, "items"),
+            )?;
             if let Some(type_ref) = t_opt {
                 let prop = Property {
                     optional: !required,
                     array: true,
                     name: entry_name.clone(),
+                    json_name: prop_name.clone(),
                     title,
                     description,
                     type_ref,
@@ -104,6 +153,7 @@ pub fn parse_props(
                     optional: !required,
                     array: true,
                     name: entry_name.clone(),
+                    json_name: prop_name.clone(),
                     title,
                     description,
                     type_ref: TypeRef {
@@ -116,15 +166,38 @@ pub fn parse_props(
             }
         } else if _type == Some("object".to_owned()) {
             // We encountered a nested object.
-            let sub_tree = parent_tree.get_or_add_tree(&entry_name)?;
-            let ns = format!("{}::{}", &parent_obj.ns, &entry_name);
-            let name = entry_name.to_case(convert_case::Case::Pascal);
-            parse_object(&entry_name, &ns, &name, prop_map, sub_tree, resolver)?;
+            let mod_name = module_name(
+                parent_tree,
+                &parent_obj.ns,
+                &ctx.idents
+                    .module(&prop_name.to_case(convert_case::Case::Snake)),
+                parent_module(&parent_obj.ns),
+                ctx.idents,
+                &prop_pointer,
+            );
+            let added = parent_tree.get_or_add_tree(&mod_name);
+            let Some(sub_tree) = ctx.diagnostics.check(added, &prop_pointer) else {
+                continue;
+            };
+            let ns = format!("{}::{}", &parent_obj.ns, &mod_name);
+            let name = ctx
+                .idents
+                .type_name(&mod_name.to_case(convert_case::Case::Pascal));
+            parse_object(
+                &mod_name,
+                &ns,
+                &name,
+                prop_map,
+                sub_tree,
+                ctx,
+                &prop_pointer,
+            )?;
             // We added the object.  Now add the property
             let prop = Property {
                 optional: !required,
                 array: false,
                 name: entry_name.clone(),
+                json_name: prop_name.clone(),
                 title,
                 description,
                 type_ref: TypeRef::from_type(&SchemaType::new(&ns, &name)),
@@ -132,7 +205,12 @@ pub fn parse_props(
             };
             props.insert(entry_name, prop);
         } else {
-            println!("Unhandled property type: {:?}", prop_map);
+            let message = match &_type {
+                Some(_type) => format!("Properties of type {} are not supported, left out", _type),
+                None => "No $ref, anyOf or type, left out".to_owned(),
+            };
+            ctx.diagnostics
+                .warn(Code::UnsupportedProperty, &prop_pointer, &message);
         }
     }
 
@@ -142,3 +220,17 @@ pub fn parse_props(
 
     Ok(())
 }
+
+/// `name`, or if another field has it, the first of `name_2`, `name_3` and
+/// so on that is free.  JSON names such as `a-b` and `a_b` are the same
+/// field name.
+fn unique_field(props: &IndexMap<String, Property>, name: &str) -> String {
+    let base = unraw(name);
+    let mut renamed = name.to_owned();
+    let mut count = 2;
+    while props.contains_key(&renamed) {
+        renamed = format!("{}_{}", base, count);
+        count += 1;
+    }
+    renamed
+}
@@ -1,32 +1,57 @@
 use anyhow::Result;
+use convert_case::{Case, Casing};
+use indexmap::IndexMap;
 use regex::Regex;
 use serde_json::{Map, Value};
 
-use crate::{is_datatype, util::*, Error, NamespaceEntry, Resolver, Schema};
+use crate::{
+    is_datatype, pointer, util::*, Code, Diagnostics, Error, Identifiers, NamespaceEntry, Resolver,
+    Schema, TypeRef,
+};
 
-use super::parse_object;
+use super::{get_required, module_name, parse_object, ParseContext};
 
-pub fn parse_schema(value: &Value, resolver: &mut Resolver) -> Result<Schema> {
-    let schema_map = value_to_map(value)?;
-
-    let schema = try_string_entry("$schema", schema_map)?;
-    let id = try_string_entry("$id", schema_map)?;
-    let version = parse_version_from_id(id)?;
-    let comment = try_string_entry("$comment", schema_map)?;
+/// Parse the schema into a tree.  Every problem goes to `diagnostics`.
+/// Parsing goes on past those in a definition, leaving it out, and fails
+/// on those in the schema as a whole.
+pub fn parse_schema(
+    value: &Value,
+    resolver: &mut Resolver,
+    idents: &Identifiers,
+    diagnostics: &mut Diagnostics,
+) -> Result<Schema> {
+    let header = |diagnostics: &mut Diagnostics| {
+        let schema_map = diagnostics.check(value_to_map(value), "")?;
+        let schema = diagnostics.check(try_string_entry("$schema", schema_map), "")?;
+        let id = diagnostics.check(try_string_entry("$id", schema_map), "")?;
+        let version = diagnostics.check(parse_version_from_id(id), &pointer("", "$id"))?;
+        let comment = diagnostics.check(try_string_entry("$comment", schema_map), "")?;
+        let definitions = diagnostics.check(try_map_entry("definitions", schema_map), "")?;
+        Some((schema_map, schema, version, comment, definitions))
+    };
+    let Some((schema_map, schema, version, comment, definitions)) = header(diagnostics) else {
+        return Err(Error::SchemaErrors(1).into());
+    };
 
     let mut crate_tree = Schema::new(schema, &version, comment);
-    parse_definitions(schema_map, &mut crate_tree, resolver)?;
+    let mut ctx = ParseContext {
+        resolver,
+        idents,
+        diagnostics,
+    };
+    parse_definitions(definitions, &mut crate_tree, &mut ctx)?;
+    crate_tree.documents = parse_documents(schema_map, ctx.diagnostics);
 
-    //println!("{}", &tree);
     Ok(crate_tree)
 }
 
 fn parse_definitions(
-    schema_map: &Map<String, Value>,
+    definitions: &Map<String, Value>,
     crate_tree: &mut Schema,
-    resolver: &mut Resolver,
+    ctx: &mut ParseContext,
 ) -> Result<()> {
-    let definitions = try_map_entry("definitions", schema_map)?;
+    let definitions_pointer = pointer("", "definitions");
+    let idents = ctx.idents;
 
     for (name, value) in definitions {
         if is_datatype(name) {
@@ -34,40 +59,420 @@ fn parse_definitions(
             continue;
         }
 
+        let definition_pointer = pointer(&definitions_pointer, name);
+        let Some(map) = ctx
+            .diagnostics
+            .check(value_to_map(value), &definition_pointer)
+        else {
+            continue;
+        };
+        let id = try_string_entry("$id", map).ok();
+        if ctx.resolver.apply_override(name, id.map(|id| id.as_str())) {
+            // Bound to a Rust type by the config.  Don't generate it.
+            continue;
+        }
+
         let names = NamespaceEntry::from(name.as_ref());
-        let map = value_to_map(value)?;
+        let left = idents.module(&names.left);
+        let default_right = idents.module(&names.right);
+        // Renamed modules, to resolve a collision or by the config, also
+        // rename the type
+        let type_name = |right: &str| match right == default_right {
+            true => idents.type_name(&names.rust),
+            false => idents.type_name(&right.to_case(Case::Pascal)),
+        };
         // So, now we have 2 choices.
         // 1. The object is a top level object, like StringDatatype
         // 2. The object is a 3 part name
         if names.left.is_empty() {
             // Type 1: top level object
+            let right = module_name(
+                crate_tree,
+                "",
+                &default_right,
+                "",
+                idents,
+                &definition_pointer,
+            );
+            let rust = type_name(&right);
             parse_object(
-                &names.right,
-                &names.right,
-                &names.rust,
+                &right,
+                &right,
+                &rust,
                 map,
                 crate_tree,
-                resolver,
+                ctx,
+                &definition_pointer,
             )?;
         } else {
             // Type 2, multiple names
             // Add or get the first part
-            let left_tree = crate_tree.get_or_add_tree(&names.left)?;
-            let right_tree = left_tree.get_or_add_tree(&names.right)?;
+            let added = crate_tree.get_or_add_tree(&left);
+            let Some(left_tree) = ctx.diagnostics.check(added, &definition_pointer) else {
+                continue;
+            };
+            let right = module_name(
+                left_tree,
+                &left,
+                &default_right,
+                "",
+                idents,
+                &definition_pointer,
+            );
+            let rust = type_name(&right);
+            let added = left_tree.get_or_add_tree(&right);
+            let Some(right_tree) = ctx.diagnostics.check(added, &definition_pointer) else {
+                continue;
+            };
 
-            let ns = format!("{}::{}", &names.left, &names.right);
-            parse_object(&names.right, &ns, &names.rust, map, right_tree, resolver)?;
+            let ns = format!("{}::{}", &left, &right);
+            parse_object(
+                &right,
+                &ns,
+                &rust,
+                map,
+                right_tree,
+                ctx,
+                &definition_pointer,
+            )?;
         }
     }
 
     Ok(())
 }
 
+/// The root document types are the root `properties`, either directly or
+/// within the `oneOf` alternatives.  If the `oneOf` alternatives name their
+/// required properties, only those are documents.  `$schema` never is.
+fn parse_documents(
+    schema_map: &Map<String, Value>,
+    diagnostics: &mut Diagnostics,
+) -> IndexMap<String, TypeRef> {
+    // Each `properties` map, with its pointer
+    let mut props = vec![];
+    let mut requireds = vec![];
+
+    if let Ok(root_props) = try_map_entry("properties", schema_map) {
+        props.push((pointer("", "properties"), root_props));
+    }
+    if let Ok(one_of) = try_array_entry("oneOf", schema_map) {
+        for (index, alternative) in one_of.iter().enumerate() {
+            let one_of_pointer = pointer(&pointer("", "oneOf"), &index.to_string());
+            let Some(alternative) = diagnostics.check(value_to_map(alternative), &one_of_pointer)
+            else {
+                continue;
+            };
+            if let Ok(alt_props) = try_map_entry("properties", alternative) {
+                props.push((pointer(&one_of_pointer, "properties"), alt_props));
+            }
+            let required_pointer = pointer(&one_of_pointer, "required");
+            if let Some(mut required) =
+                diagnostics.check(get_required(alternative), &required_pointer)
+            {
+                requireds.append(&mut required);
+            }
+        }
+    }
+
+    let mut documents = IndexMap::new();
+    let props = props.into_iter().flat_map(|(props_pointer, props)| {
+        props.iter().map(move |prop| (props_pointer.clone(), prop))
+    });
+    for (props_pointer, (name, value)) in props {
+        if name == "$schema" || documents.contains_key(name) {
+            continue;
+        }
+        if !requireds.is_empty() && !requireds.contains(name) {
+            continue;
+        }
+        let prop_pointer = pointer(&props_pointer, name);
+        let Some(map) = diagnostics.check(value_to_map(value), &prop_pointer) else {
+            continue;
+        };
+        let Ok(_ref) = try_string_entry("$ref", map) else {
+            diagnostics.warn(
+                Code::DocumentWithoutRef,
+                &prop_pointer,
+                "Root document property without a $ref, left out",
+            );
+            continue;
+        };
+        documents.insert(name.to_owned(), TypeRef::from_ref(Some(_ref.to_owned())));
+    }
+    documents
+}
+
+/// The version in the `$id` of the complete schema, or of a single model
+/// schema such as `oscal-catalog-schema.json`
 fn parse_version_from_id(id: &str) -> Result<String> {
     let r = Regex::new(
-        r##"^http://csrc.nist.gov/ns/oscal/1.0/(?<version>[1-9][0-9]?\.\d+\.\d+)/oscal-complete-schema.json$"##,
+        r##"^http://csrc.nist.gov/ns/oscal/1.0/(?<version>[1-9][0-9]?\.\d+\.\d+)/oscal-[a-z][a-z-]*-schema.json$"##,
     )?;
     let caps = r.captures(id).ok_or(Error::VersionParse)?;
     let version = caps["version"].to_owned();
     Ok(version)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn documents(schema: Value) -> (Vec<(String, String)>, Diagnostics) {
+        let mut diagnostics = Diagnostics::new();
+        let documents = parse_documents(value_to_map(&schema).expect("oops"), &mut diagnostics)
+            .into_iter()
+            .map(|(key, type_ref)| (key, type_ref._ref.expect("oops")))
+            .collect();
+        (documents, diagnostics)
+    }
+
+    #[test]
+    fn test_root_properties() {
+        let (documents, diagnostics) = documents(json!({
+            "properties": {
+                "$schema": { "type": "string", "format": "uri-reference" },
+                "catalog": { "$ref": "#assembly_oscal-catalog_catalog" },
+                "profile": { "$ref": "#assembly_oscal-profile_profile" }
+            }
+        }));
+        assert_eq!(
+            documents,
+            vec![
+                (
+                    "catalog".to_owned(),
+                    "#assembly_oscal-catalog_catalog".to_owned()
+                ),
+                (
+                    "profile".to_owned(),
+                    "#assembly_oscal-profile_profile".to_owned()
+                ),
+            ]
+        );
+        assert_eq!(diagnostics.iter().count(), 0);
+    }
+
+    #[test]
+    fn test_one_of_alternatives() {
+        let (documents, diagnostics) = documents(json!({
+            "properties": {
+                "$schema": { "type": "string" },
+                "catalog": { "$ref": "#assembly_oscal-catalog_catalog" },
+                "profile": { "$ref": "#assembly_oscal-profile_profile" },
+                "extra": { "type": "string" }
+            },
+            "oneOf": [
+                { "required": ["catalog"] },
+                {
+                    "properties": {
+                        "mapping": { "$ref": "#assembly_oscal-mapping_mapping" },
+                        "notes": { "type": "string" }
+                    },
+                    "required": ["mapping", "notes"]
+                }
+            ]
+        }));
+        // Only the required properties are documents, from wherever they are
+        assert_eq!(
+            documents,
+            vec![
+                (
+                    "catalog".to_owned(),
+                    "#assembly_oscal-catalog_catalog".to_owned()
+                ),
+                (
+                    "mapping".to_owned(),
+                    "#assembly_oscal-mapping_mapping".to_owned()
+                ),
+            ]
+        );
+        let pointers: Vec<&str> = diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.pointer.as_str())
+            .collect();
+        assert_eq!(pointers, vec!["/oneOf/1/properties/notes"]);
+    }
+
+    #[test]
+    fn test_malformed_schema() {
+        let parse = |schema: Value| {
+            let mut diagnostics = Diagnostics::new();
+            let parsed = parse_schema(
+                &schema,
+                &mut Resolver::new(),
+                &Identifiers::default(),
+                &mut diagnostics,
+            );
+            let reported: Vec<String> = diagnostics
+                .iter()
+                .map(|diagnostic| format!("{} {}", diagnostic.code, diagnostic.pointer))
+                .collect();
+            (parsed.is_ok(), reported)
+        };
+
+        let (parsed, reported) = parse(json!({
+            "$schema": "http://json-schema.org/draft-07/schema#",
+            "$id": "http://csrc.nist.gov/ns/oscal/1.0/1.1.2/oscal-complete-schema.json",
+            "$comment": "OSCAL Unified Model of Models: JSON Schema",
+            "definitions": {
+                "oscal-complete-oscal-catalog:group": {
+                    "$id": "#assembly_oscal-catalog_group",
+                    "type": "object",
+                    "properties": {
+                        "id": { "$ref": "#/definitions/TokenDatatype" },
+                        "title": "not an object",
+                        "parts": { "type": "array", "items": { "anyOf": {} } }
+                    },
+                    "required": ["id"]
+                },
+                "oscal-complete-oscal-catalog:param": {
+                    "$id": "#assembly_oscal-catalog_param",
+                    "type": "object",
+                    "properties": { "id": { "$ref": "#/definitions/TokenDatatype" } },
+                    "required": [1]
+                },
+                "oscal-complete-oscal-catalog:part": []
+            }
+        }));
+        // Malformed definitions are reported where they are, and left out
+        assert!(parsed);
+        assert_eq!(
+            reported,
+            vec![
+                "malformed-schema /definitions/oscal-complete-oscal-catalog:group/properties/title",
+                "malformed-schema /definitions/oscal-complete-oscal-catalog:group/properties/parts/items",
+                "malformed-schema /definitions/oscal-complete-oscal-catalog:param/required",
+                "malformed-schema /definitions/oscal-complete-oscal-catalog:part",
+            ]
+        );
+
+        let (parsed, reported) = parse(json!({
+            "$schema": "http://json-schema.org/draft-07/schema#",
+            "$id": "https://example.com/schema.json",
+            "$comment": "",
+            "definitions": {}
+        }));
+        // A malformed schema as a whole stops parsing
+        assert!(!parsed);
+        assert_eq!(reported, vec!["malformed-schema /$id"]);
+    }
+
+    #[test]
+    fn test_single_model_schema() {
+        let mut diagnostics = Diagnostics::new();
+        let mut resolver = Resolver::new();
+        let schema = parse_schema(
+            &json!({
+                "$schema": "http://json-schema.org/draft-07/schema#",
+                "$id": "http://csrc.nist.gov/ns/oscal/1.0/1.1.2/oscal-catalog-schema.json",
+                "$comment": "OSCAL Control Catalog Model: JSON Schema",
+                "definitions": {
+                    "oscal-catalog-oscal-catalog:catalog": {
+                        "$id": "#assembly_oscal-catalog_catalog",
+                        "type": "object",
+                        "properties": {
+                            "uuid": { "$ref": "#/definitions/UUIDDatatype" }
+                        },
+                        "required": ["uuid"]
+                    }
+                },
+                "properties": {
+                    "$schema": { "type": "string", "format": "uri-reference" },
+                    "catalog": { "$ref": "#assembly_oscal-catalog_catalog" }
+                },
+                "required": ["catalog"]
+            }),
+            &mut resolver,
+            &Identifiers::default(),
+            &mut diagnostics,
+        )
+        .expect("oops");
+
+        assert_eq!(schema.version, "1.1.2");
+        assert_eq!(diagnostics.iter().count(), 0);
+        let documents: Vec<&String> = schema.documents.keys().collect();
+        assert_eq!(documents, vec!["catalog"]);
+        let catalog = resolver
+            .resolve(&schema.documents["catalog"])
+            .expect("oops");
+        assert_eq!(catalog.path(), "oscal_catalog::catalog::Catalog");
+        assert!(schema
+            .get_tree("oscal_catalog")
+            .and_then(|tree| tree.get_tree("catalog"))
+            .is_ok());
+        assert!(parse_version_from_id(
+            "http://csrc.nist.gov/ns/oscal/1.0/1.1.2/oscal-complete-schema.json"
+        )
+        .is_ok());
+        assert!(
+            parse_version_from_id("http://csrc.nist.gov/ns/oscal/1.0/1.1.2/catalog.json").is_err()
+        );
+    }
+
+    #[test]
+    fn test_field_collisions() {
+        let mut diagnostics = Diagnostics::new();
+        let schema = parse_schema(
+            &json!({
+                "$schema": "http://json-schema.org/draft-07/schema#",
+                "$id": "http://csrc.nist.gov/ns/oscal/1.0/1.1.2/oscal-complete-schema.json",
+                "$comment": "",
+                "definitions": {
+                    "oscal-complete-oscal-metadata:property": {
+                        "$id": "#field_oscal-metadata_property",
+                        "type": "object",
+                        "properties": {
+                            "a.b": { "type": "string" },
+                            "a-b": { "type": "string" },
+                            "a_b": { "type": "string" },
+                            "type": { "type": "string" },
+                            "Type": { "type": "string" }
+                        }
+                    }
+                }
+            }),
+            &mut Resolver::new(),
+            &Identifiers::default(),
+            &mut diagnostics,
+        )
+        .expect("oops");
+
+        // No field is lost, and serde still reads each from its own name
+        let obj = schema.objects()[0];
+        let fields: Vec<(&str, &str)> = obj
+            .props
+            .iter()
+            .flatten()
+            .map(|(name, prop)| (name.as_str(), prop.json_name.as_str()))
+            .collect();
+        assert_eq!(
+            fields,
+            vec![
+                ("a_b", "a.b"),
+                ("a_b_2", "a-b"),
+                ("a_b_3", "a_b"),
+                ("r#type", "type"),
+                ("type_2", "Type"),
+            ]
+        );
+        let reported: Vec<String> = diagnostics.iter().map(ToString::to_string).collect();
+        let pointer = "/definitions/oscal-complete-oscal-metadata:property/properties";
+        assert_eq!(
+            reported,
+            vec![
+                format!(
+                    "warning[name-collision] {}/a-b: `a_b` collides with another field, renamed to `a_b_2`",
+                    pointer
+                ),
+                format!(
+                    "warning[name-collision] {}/a_b: `a_b` collides with another field, renamed to `a_b_3`",
+                    pointer
+                ),
+                format!(
+                    "warning[name-collision] {}/Type: `r#type` collides with another field, renamed to `type_2`",
+                    pointer
+                ),
+            ]
+        );
+    }
+}
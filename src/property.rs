@@ -1,14 +1,16 @@
-use serde::Serialize;
+use convert_case::{Case, Casing};
+use serde::{Deserialize, Serialize};
 
-use crate::TypeRef;
+use crate::{unraw, TypeRef};
 
-const RESERVED_NAMES: [&str; 2] = ["type", "ref"];
-
-#[derive(Debug, Clone, PartialEq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Property {
     pub optional: bool,
     pub array: bool,
+    /// The Rust field name, made a valid identifier
     pub name: String,
+    /// The property name in the schema
+    pub json_name: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub title: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -19,12 +21,48 @@ pub struct Property {
 }
 
 impl Property {
-    /// Returns the name, with a starting `_`, if it is
-    /// reserved, along with an indicator.
-    pub fn name(&self) -> (String, String, bool) {
-        match RESERVED_NAMES.contains(&self.name.as_str()) {
-            true => (format!("_{}", &self.name), self.name.clone(), true),
-            false => (self.name.clone(), self.name.clone(), false),
+    /// An optional property of the type `_ref`, for tests to fill in
+    #[cfg(test)]
+    pub fn test_property(name: &str, json_name: &str, _ref: &str) -> Self {
+        Self {
+            optional: true,
+            array: false,
+            name: name.to_owned(),
+            json_name: json_name.to_owned(),
+            title: None,
+            description: None,
+            type_ref: TypeRef::from_ref(Some(_ref.to_owned())),
+            enums: None,
         }
     }
+
+    /// Whether the field needs a `#[serde(rename)]` to the JSON name.
+    /// That is the case for fields whose name had to be made a valid
+    /// identifier, such as `r#type` or `_1st`, and for JSON names that
+    /// `rename_all = "kebab-case"` doesn't get back to, such as `$schema`,
+    /// `ipv4-address` or `camelCase`.
+    pub fn needs_rename(&self) -> bool {
+        let sanitized = self.name != self.json_name.to_case(Case::Snake);
+        let kebab = unraw(&self.name).replace('_', "-");
+        sanitized || kebab != self.json_name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn property(name: &str, json_name: &str) -> Property {
+        Property::test_property(name, json_name, "#/definitions/StringDatatype")
+    }
+
+    #[test]
+    fn test_needs_rename() {
+        assert!(!property("last_modified", "last-modified").needs_rename());
+        assert!(property("r#type", "type").needs_rename());
+        assert!(property("schema", "$schema").needs_rename());
+        assert!(property("ipv_4_address", "ipv4-address").needs_rename());
+        assert!(property("a_b", "a--b").needs_rename());
+        assert!(property("camel_case", "camelCase").needs_rename());
+    }
 }
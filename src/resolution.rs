@@ -0,0 +1,193 @@
+//! The resolution pass.  Every reference in the tree is checked once parsing
+//! is done, so that all dangling references are reported together, before
+//! any file is written, rather than one per run.
+use crate::{pointer, Code, Diagnostics, Resolver, Schema, SchemaType, TypeRef};
+
+/// How many references are suggested for a dangling one
+const MAX_SUGGESTIONS: usize = 3;
+/// How close, from 0 to 1, a reference must be to be suggested
+const MIN_SIMILARITY: f64 = 0.85;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DanglingRef {
+    /// The `$id` of the referring definition, or its path, or the location
+    /// for root documents
+    pub definition: String,
+    /// Where the reference is, such as `oscal_catalog::group::Group.parts`
+    pub location: String,
+    /// Where the reference is in the schema, as a JSON pointer
+    pub pointer: String,
+    /// The reference, or nothing if the type had neither `$ref` nor type
+    pub _ref: Option<String>,
+    /// The known references closest to it, closest first
+    pub suggestions: Vec<String>,
+}
+
+impl std::fmt::Display for DanglingRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self._ref {
+            Some(_ref) => write!(f, "{}: unresolved {}", self.location, _ref)?,
+            None => write!(f, "{}: no $ref or type", self.location)?,
+        }
+        if self.definition != self.location {
+            write!(f, " (in {})", self.definition)?;
+        }
+        if !self.suggestions.is_empty() {
+            write!(f, ", did you mean {}?", self.suggestions.join(" or "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Every reference in the tree that doesn't resolve, in tree order
+pub fn dangling_refs(tree: &Schema, resolver: &Resolver) -> Vec<DanglingRef> {
+    let mut result = vec![];
+    let mut check =
+        |definition: &str, location: String, ref_pointer: String, type_ref: &TypeRef| {
+            if resolver.resolve(type_ref).is_some() {
+                return;
+            }
+            result.push(DanglingRef {
+                definition: definition.to_owned(),
+                location,
+                pointer: ref_pointer,
+                _ref: type_ref._ref.clone(),
+                suggestions: type_ref
+                    ._ref
+                    .as_deref()
+                    .map(|_ref| suggestions(_ref, resolver))
+                    .unwrap_or_default(),
+            });
+        };
+
+    for (key, type_ref) in &tree.documents {
+        let location = format!("documents.{}", key);
+        let ref_pointer = pointer(&pointer("", "properties"), key);
+        check(&location, location.clone(), ref_pointer, type_ref);
+    }
+    for obj in tree.objects() {
+        let path = SchemaType::from(obj).path();
+        let definition = obj.id.clone().unwrap_or(path.clone());
+        let obj_pointer = obj.pointer.clone().unwrap_or_default();
+        if obj.type_ref.is_some() {
+            check(
+                &definition,
+                path.clone(),
+                obj_pointer.clone(),
+                &TypeRef::from_ref(obj.type_ref.clone()),
+            );
+        }
+        let props_pointer = pointer(&obj_pointer, "properties");
+        for prop in obj.props.iter().flat_map(|props| props.values()) {
+            // The `$ref` of an array is in its `items`
+            let prop_pointer = pointer(&props_pointer, &prop.json_name);
+            let ref_pointer = match prop.array {
+                true => pointer(&prop_pointer, "items"),
+                false => prop_pointer,
+            };
+            check(
+                &definition,
+                format!("{}.{}", path, prop.json_name),
+                ref_pointer,
+                &prop.type_ref,
+            );
+        }
+    }
+    result
+}
+
+/// Report every dangling reference as an error, where it is in the schema
+pub fn check_refs(tree: &Schema, resolver: &Resolver, diagnostics: &mut Diagnostics) {
+    for dangling in dangling_refs(tree, resolver) {
+        diagnostics.error(
+            Code::UnresolvedRef,
+            &dangling.pointer,
+            &dangling.to_string(),
+        );
+    }
+}
+
+/// The known references most like `_ref`
+fn suggestions(_ref: &str, resolver: &Resolver) -> Vec<String> {
+    let mut scored: Vec<(f64, &String)> = resolver
+        .iter()
+        .map(|(known, _)| (strsim::normalized_levenshtein(_ref, known), known))
+        .filter(|(score, _)| *score >= MIN_SIMILARITY)
+        .collect();
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+    scored
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, known)| known.to_owned())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DiagnosticsFormat, Property, SchemaObject};
+    use indexmap::IndexMap;
+
+    #[test]
+    fn test_dangling_refs() {
+        let mut resolver = Resolver::new();
+        resolver.add_type(
+            "#assembly_oscal-catalog_group",
+            SchemaType::new("group", "Group"),
+        );
+
+        let prop = |name: &str, _ref: &str, array: bool| {
+            let prop = Property {
+                array,
+                ..Property::test_property(name, &name.replace('_', "-"), _ref)
+            };
+            (name.to_owned(), prop)
+        };
+        let obj = SchemaObject {
+            pointer: Some("/definitions/oscal-complete-oscal-catalog:group".to_owned()),
+            id: Some("#assembly_oscal-catalog_group".to_owned()),
+            _type: Some("object".to_owned()),
+            props: Some(IndexMap::from([
+                prop("groups", "#assembly_oscal-catalog_grop", true),
+                prop("title", "#/definitions/StringDatatype", false),
+                prop("sub_parts", "#assembly_oscal-catalog_part", false),
+            ])),
+            ..SchemaObject::test_object("group", "Group")
+        };
+        let mut tree = Schema::new("", "1.1.2", "");
+        tree.add_object("group", &obj).expect("oops");
+        tree.documents.insert(
+            "catalog".to_owned(),
+            TypeRef::from_ref(Some("#assembly_oscal-catalog_catalog".to_owned())),
+        );
+
+        let dangling: Vec<String> = dangling_refs(&tree, &resolver)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            dangling,
+            vec![
+                "documents.catalog: unresolved #assembly_oscal-catalog_catalog",
+                "group::Group.groups: unresolved #assembly_oscal-catalog_grop (in #assembly_oscal-catalog_group), did you mean #assembly_oscal-catalog_group?",
+                "group::Group.sub-parts: unresolved #assembly_oscal-catalog_part (in #assembly_oscal-catalog_group)",
+            ]
+        );
+
+        let mut diagnostics = Diagnostics::new();
+        check_refs(&tree, &resolver, &mut diagnostics);
+        let reported: Vec<String> = diagnostics
+            .iter()
+            .map(|diagnostic| format!("{} {}", diagnostic.code, diagnostic.pointer))
+            .collect();
+        assert_eq!(
+            reported,
+            vec![
+                "unresolved-ref /properties/catalog",
+                "unresolved-ref /definitions/oscal-complete-oscal-catalog:group/properties/groups/items",
+                "unresolved-ref /definitions/oscal-complete-oscal-catalog:group/properties/sub-parts",
+            ]
+        );
+        assert!(diagnostics.report(DiagnosticsFormat::Json, false).is_err());
+    }
+}
@@ -1,15 +1,38 @@
-use std::collections::BTreeMap;
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
 
-use crate::{SchemaType, TypeRef, DATA_TYPES};
+use crate::{strip_oscal, SchemaType, TypeRef, DATA_TYPES};
 
+/// Maps schema references to Rust types.  Only the map is part of the
+/// intermediate representation, overrides are already applied to it.
+#[derive(Serialize, Deserialize)]
 pub struct Resolver {
     map: BTreeMap<String, SchemaType>,
+    /// Schema definitions bound to Rust types outside of the generated crate.
+    /// Keyed by `$id`, `$ref`, JSON pointer or definition name.
+    #[serde(skip)]
+    overrides: IndexMap<String, SchemaType>,
+    /// The override keys that matched a definition
+    #[serde(skip)]
+    used_overrides: BTreeSet<String>,
 }
 
 impl Resolver {
     pub fn new() -> Self {
+        Self::with_overrides(&IndexMap::new())
+    }
+
+    /// Create a resolver that maps the given schema definitions to Rust paths,
+    /// rather than to generated types.
+    pub fn with_overrides(overrides: &IndexMap<String, String>) -> Self {
         let mut result = Self {
             map: BTreeMap::new(),
+            overrides: overrides
+                .iter()
+                .map(|(key, path)| (key.to_owned(), SchemaType::external(path)))
+                .collect(),
+            used_overrides: BTreeSet::new(),
         };
         load_data_types(&mut result);
         result
@@ -19,6 +42,11 @@ impl Resolver {
         self.map.insert(id.to_owned(), type_ref);
     }
 
+    /// Every reference, with the type it resolves to
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &SchemaType)> {
+        self.map.iter()
+    }
+
     pub fn resolve(&self, type_ref: &TypeRef) -> Option<SchemaType> {
         // If the type_ref contains a _ref,
         if let Some(_ref) = &type_ref._ref {
@@ -28,6 +56,46 @@ impl Resolver {
         }
         None
     }
+
+    /// If the definition `name`, with optional `$id`, is overridden, register
+    /// the override for every way the definition can be referenced, and
+    /// return true.  Overridden definitions are not generated.
+    pub fn apply_override(&mut self, name: &str, id: Option<&str>) -> bool {
+        let pointer = format!("#/definitions/{}", name);
+        let mut keys = vec![
+            name.to_owned(),
+            strip_oscal(name).to_owned(),
+            pointer[1..].to_owned(),
+            pointer.clone(),
+        ];
+        if let Some(id) = id {
+            keys.push(id.to_owned());
+        }
+
+        let Some(key) = keys
+            .into_iter()
+            .find(|key| self.overrides.contains_key(key))
+        else {
+            return false;
+        };
+        let schema_type = self.overrides[&key].clone();
+        self.used_overrides.insert(key);
+
+        self.add_type(&pointer, schema_type.clone());
+        if let Some(id) = id {
+            self.add_type(id, schema_type);
+        }
+        true
+    }
+
+    /// Override keys that did not match any definition
+    pub fn unused_overrides(&self) -> Vec<&str> {
+        self.overrides
+            .keys()
+            .filter(|key| !self.used_overrides.contains(*key))
+            .map(|key| key.as_str())
+            .collect()
+    }
 }
 
 impl Default for Resolver {
@@ -38,9 +106,57 @@ impl Default for Resolver {
 
 fn load_data_types(map: &mut Resolver) {
     for data_type in DATA_TYPES {
+        if map.apply_override(data_type, None) {
+            continue;
+        }
         map.add_type(
             &format!("#/definitions/{}", data_type),
             SchemaType::new("oscal_types", data_type),
         );
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_data_type_override() {
+        let overrides = IndexMap::from([("UUIDDatatype".to_owned(), "uuid::Uuid".to_owned())]);
+        let resolver = Resolver::with_overrides(&overrides);
+
+        let uuid = TypeRef::from_ref(Some("#/definitions/UUIDDatatype".to_owned()));
+        assert_eq!(
+            resolver.resolve(&uuid),
+            Some(SchemaType::external("uuid::Uuid"))
+        );
+
+        let token = TypeRef::from_ref(Some("#/definitions/TokenDatatype".to_owned()));
+        assert_eq!(
+            resolver.resolve(&token),
+            Some(SchemaType::new("oscal_types", "TokenDatatype"))
+        );
+        assert!(resolver.unused_overrides().is_empty());
+    }
+
+    #[test]
+    fn test_definition_override() {
+        let overrides = IndexMap::from([(
+            "oscal-metadata:metadata".to_owned(),
+            "my_oscal::Metadata".to_owned(),
+        )]);
+        let mut resolver = Resolver::with_overrides(&overrides);
+
+        assert!(!resolver.apply_override("oscal-complete-oscal-metadata:remarks", None));
+        assert!(resolver.apply_override(
+            "oscal-complete-oscal-metadata:metadata",
+            Some("#assembly_oscal-metadata_metadata")
+        ));
+
+        let metadata = TypeRef::from_ref(Some("#assembly_oscal-metadata_metadata".to_owned()));
+        assert_eq!(
+            resolver.resolve(&metadata),
+            Some(SchemaType::external("my_oscal::Metadata"))
+        );
+    }
+}
@@ -1,8 +1,9 @@
 #![allow(dead_code)]
 use anyhow::Result;
 use indexmap::{map::Iter, IndexMap};
+use serde::{Deserialize, Serialize};
 
-use crate::{SchemaObject, TreeEntry};
+use crate::{SchemaObject, TreeEntry, TypeRef};
 
 #[derive(Debug, PartialEq, thiserror::Error)]
 pub enum Error {
@@ -14,11 +15,39 @@ pub enum Error {
     NotTree(String),
 }
 
+/// Two names that would have been the same, and what one was renamed to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Collision {
+    /// The module, or file, the names collided in
+    pub path: String,
+    pub name: String,
+    pub renamed: String,
+    /// Where the renamed item is in the schema, as a JSON pointer
+    #[serde(default)]
+    pub pointer: String,
+}
+
+impl std::fmt::Display for Collision {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: `{}` collides, renamed to `{}`",
+            self.path, self.name, self.renamed
+        )
+    }
+}
+
+#[derive(Serialize, Deserialize)]
 pub struct Schema {
     pub schema: String,
     pub version: String,
     pub comment: String,
+    /// The root document types, keyed by their JSON property name, such as
+    /// `catalog`.  Only set on the crate level tree.
+    pub documents: IndexMap<String, TypeRef>,
     tree: IndexMap<String, TreeEntry>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    collisions: Vec<Collision>,
 }
 
 impl Schema {
@@ -27,7 +56,9 @@ impl Schema {
             schema: schema.to_owned(),
             version: version.to_owned(),
             comment: comment.to_owned(),
+            documents: IndexMap::new(),
             tree: IndexMap::new(),
+            collisions: vec![],
         }
     }
     pub fn iter(&self) -> Iter<'_, String, TreeEntry> {
@@ -81,8 +112,10 @@ impl Schema {
     }
 
     pub fn add_tree(&mut self, name: &str) -> Result<()> {
-        self.tree
-            .insert(name.to_owned(), TreeEntry::Tree(Self::new("", "", "")));
+        self.tree.insert(
+            name.to_owned(),
+            TreeEntry::Tree(Box::new(Self::new("", "", ""))),
+        );
 
         Ok(())
     }
@@ -94,6 +127,93 @@ impl Schema {
         self.get_mut(name)?.as_tree_mut()
     }
 
+    /// Every object in the tree, depth first, in tree order
+    pub fn objects(&self) -> Vec<&SchemaObject> {
+        let mut result = vec![];
+        for entry in self.tree.values() {
+            match entry {
+                TreeEntry::Object(obj) => result.push(obj.as_ref()),
+                TreeEntry::Tree(tree) => result.append(&mut tree.objects()),
+            }
+        }
+        result
+    }
+
+    /// Every object in the tree, depth first, in tree order
+    pub fn objects_mut(&mut self) -> Vec<&mut SchemaObject> {
+        let mut result = vec![];
+        for entry in self.tree.values_mut() {
+            match entry {
+                TreeEntry::Object(obj) => result.push(obj.as_mut()),
+                TreeEntry::Tree(tree) => result.append(&mut tree.objects_mut()),
+            }
+        }
+        result
+    }
+
+    /// Add a complete tree, such as that of a whole schema
+    pub fn insert_tree(&mut self, name: &str, tree: Self) {
+        self.tree
+            .insert(name.to_owned(), TreeEntry::Tree(Box::new(tree)));
+    }
+
+    /// The trees of each schema version, in a tree merged from several.
+    /// Those are the subtrees with a version, since only parsing a schema
+    /// sets one.
+    pub fn version_trees(&self) -> Vec<(&str, &Self)> {
+        self.tree
+            .iter()
+            .filter_map(|(name, entry)| match entry {
+                TreeEntry::Tree(tree) if !tree.version.is_empty() => {
+                    Some((name.as_str(), tree.as_ref()))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// A name for a new entry in this tree, at module path `ns`, that
+    /// doesn't collide with an existing entry.  The `qualifier` is the
+    /// module of the object that owns the tree, which is added to the tree
+    /// last, so is taken too.  A colliding name gets the qualifier as a
+    /// prefix, then a number, such as `control_part` or `control_part_2`.
+    /// Collisions are recorded, located by `pointer` into the schema.
+    pub fn unique_name(&mut self, ns: &str, name: &str, qualifier: &str, pointer: &str) -> String {
+        let taken = |tree: &Self, name: &str| tree.has(name) || name == qualifier;
+        if !taken(self, name) {
+            return name.to_owned();
+        }
+
+        let base = match qualifier.is_empty() || qualifier == name {
+            true => name.to_owned(),
+            false => format!("{}_{}", qualifier, name),
+        };
+        let mut renamed = base.clone();
+        let mut count = 2;
+        while taken(self, &renamed) {
+            renamed = format!("{}_{}", base, count);
+            count += 1;
+        }
+        self.collisions.push(Collision {
+            path: ns.to_owned(),
+            name: name.to_owned(),
+            renamed: renamed.clone(),
+            pointer: pointer.to_owned(),
+        });
+        renamed
+    }
+
+    /// Every collision in the tree, depth first
+    pub fn collisions(&self) -> Vec<Collision> {
+        let mut result = self.collisions.clone();
+        for entry in self.tree.values() {
+            if let TreeEntry::Tree(tree) = entry {
+                result.append(&mut tree.collisions());
+            }
+        }
+        result
+    }
+
     pub fn get_or_add_tree(&mut self, name: &str) -> Result<&mut Self> {
         if self.has(name) {
             return self.get_tree_mut(name);
@@ -102,3 +222,42 @@ impl Schema {
         self.get_tree_mut(name)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unique_name() {
+        let mut tree = Schema::new("", "", "");
+        tree.add_tree("part").expect("oops");
+        tree.add_tree("control_part").expect("oops");
+
+        assert_eq!(tree.unique_name("a", "prop", "control", "/a"), "prop");
+        assert_eq!(
+            tree.unique_name("a", "control", "control", "/b"),
+            "control_2"
+        );
+        assert_eq!(
+            tree.unique_name("a", "part", "control", "/c"),
+            "control_part_2"
+        );
+        assert_eq!(
+            tree.collisions(),
+            vec![
+                Collision {
+                    path: "a".to_owned(),
+                    name: "control".to_owned(),
+                    renamed: "control_2".to_owned(),
+                    pointer: "/b".to_owned(),
+                },
+                Collision {
+                    path: "a".to_owned(),
+                    name: "part".to_owned(),
+                    renamed: "control_part_2".to_owned(),
+                    pointer: "/c".to_owned(),
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,451 @@
+//! Differences between two parsed schemas, for the `diff` subcommand.
+//!
+//! Types are matched by their `$id` where they have one, since ids survive
+//! schema releases better than definition names, and by path otherwise.
+//! Fields are matched by JSON property name.
+//!
+//! Changes are classified by what they do to the generated crate.  The
+//! generated structs are exhaustive, with public fields, so any added field
+//! breaks struct literals, and is breaking.  Enum values are not part of the
+//! Rust API, so only removing one, which rejects documents that were valid,
+//! is breaking.
+use anyhow::Result;
+use clap::ValueEnum;
+use indexmap::IndexMap;
+use serde::Serialize;
+use std::fmt::Write;
+
+use crate::{Ir, Property, Resolver, SchemaObject, SchemaType, TypeRef};
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum DiffFormat {
+    Text,
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Impact {
+    Compatible,
+    Breaking,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ChangeKind {
+    TypeAdded,
+    TypeRemoved,
+    FieldAdded {
+        optional: bool,
+    },
+    FieldRemoved,
+    MadeRequired,
+    MadeOptional,
+    /// The field type, or the inner type of a newtype, changed
+    Retyped {
+        from: String,
+        to: String,
+    },
+    EnumValuesAdded {
+        values: Vec<String>,
+    },
+    EnumValuesRemoved {
+        values: Vec<String>,
+    },
+}
+
+impl ChangeKind {
+    pub fn impact(&self) -> Impact {
+        match self {
+            ChangeKind::TypeAdded | ChangeKind::EnumValuesAdded { .. } => Impact::Compatible,
+            _ => Impact::Breaking,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Change {
+    /// The type path in the new schema, or in the old one for removed types
+    pub path: String,
+    /// The JSON property name, for field changes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
+    #[serde(flatten)]
+    pub kind: ChangeKind,
+    pub impact: Impact,
+}
+
+impl std::fmt::Display for Change {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let field = self.field.as_deref().unwrap_or_default();
+        let what = match &self.kind {
+            ChangeKind::TypeAdded => "added".to_owned(),
+            ChangeKind::TypeRemoved => "removed".to_owned(),
+            ChangeKind::FieldAdded { optional: true } => format!("added optional `{}`", field),
+            ChangeKind::FieldAdded { optional: false } => format!("added required `{}`", field),
+            ChangeKind::FieldRemoved => format!("removed `{}`", field),
+            ChangeKind::MadeRequired => format!("`{}` made required", field),
+            ChangeKind::MadeOptional => format!("`{}` made optional", field),
+            ChangeKind::Retyped { from, to } => match &self.field {
+                Some(field) => format!("`{}` changed from {} to {}", field, from, to),
+                None => format!("changed from {} to {}", from, to),
+            },
+            ChangeKind::EnumValuesAdded { values } => {
+                format!("`{}` allows {}", field, values.join(", "))
+            }
+            ChangeKind::EnumValuesRemoved { values } => {
+                format!("`{}` no longer allows {}", field, values.join(", "))
+            }
+        };
+        write!(f, "{}: {}", self.path, what)
+    }
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct SchemaDiff {
+    pub old_version: String,
+    pub new_version: String,
+    pub changes: Vec<Change>,
+}
+
+impl SchemaDiff {
+    pub fn new(old: &Ir, new: &Ir) -> Self {
+        let mut result = Self {
+            old_version: old.schema.version.clone(),
+            new_version: new.schema.version.clone(),
+            changes: vec![],
+        };
+        let old_objects = old.schema.objects();
+        let new_objects = new.schema.objects();
+
+        for old_obj in &old_objects {
+            match new_objects.iter().find(|obj| same_type(old_obj, obj)) {
+                Some(new_obj) => result.diff_object(old_obj, &old.resolver, new_obj, &new.resolver),
+                None => result.push(old_obj, None, ChangeKind::TypeRemoved),
+            }
+        }
+        for new_obj in &new_objects {
+            if !old_objects.iter().any(|obj| same_type(obj, new_obj)) {
+                result.push(new_obj, None, ChangeKind::TypeAdded);
+            }
+        }
+        result
+    }
+
+    pub fn is_breaking(&self) -> bool {
+        self.changes
+            .iter()
+            .any(|change| change.impact == Impact::Breaking)
+    }
+
+    pub fn render(&self, format: DiffFormat) -> Result<String> {
+        match format {
+            DiffFormat::Text => self.to_text(),
+            DiffFormat::Json => Ok(serde_json::to_string_pretty(self)? + "\n"),
+        }
+    }
+
+    fn push(&mut self, obj: &SchemaObject, field: Option<&str>, kind: ChangeKind) {
+        self.changes.push(Change {
+            path: SchemaType::from(obj).path(),
+            field: field.map(str::to_owned),
+            impact: kind.impact(),
+            kind,
+        });
+    }
+
+    fn diff_object(
+        &mut self,
+        old: &SchemaObject,
+        old_resolver: &Resolver,
+        new: &SchemaObject,
+        new_resolver: &Resolver,
+    ) {
+        let from = inner_type(old, old_resolver);
+        let to = inner_type(new, new_resolver);
+        if from != to {
+            self.push(new, None, ChangeKind::Retyped { from, to });
+        }
+
+        let old_props = json_props(old);
+        let new_props = json_props(new);
+
+        for (name, old_prop) in &old_props {
+            let Some(new_prop) = new_props.get(name) else {
+                self.push(new, Some(name), ChangeKind::FieldRemoved);
+                continue;
+            };
+
+            match (old_prop.optional, new_prop.optional) {
+                (true, false) => self.push(new, Some(name), ChangeKind::MadeRequired),
+                (false, true) => self.push(new, Some(name), ChangeKind::MadeOptional),
+                _ => {}
+            }
+
+            let from = field_type(old_prop, old_resolver);
+            let to = field_type(new_prop, new_resolver);
+            if from != to {
+                self.push(new, Some(name), ChangeKind::Retyped { from, to });
+            }
+
+            let old_enums = old_prop.enums.clone().unwrap_or_default();
+            let new_enums = new_prop.enums.clone().unwrap_or_default();
+            let added = missing_from(&new_enums, &old_enums);
+            if !added.is_empty() {
+                let kind = ChangeKind::EnumValuesAdded { values: added };
+                self.push(new, Some(name), kind);
+            }
+            let removed = missing_from(&old_enums, &new_enums);
+            if !removed.is_empty() {
+                let kind = ChangeKind::EnumValuesRemoved { values: removed };
+                self.push(new, Some(name), kind);
+            }
+        }
+
+        for (name, new_prop) in &new_props {
+            if !old_props.contains_key(name) {
+                let kind = ChangeKind::FieldAdded {
+                    optional: new_prop.optional,
+                };
+                self.push(new, Some(name), kind);
+            }
+        }
+    }
+
+    fn to_text(&self) -> Result<String> {
+        let mut buffer = String::new();
+        let breaking = self
+            .changes
+            .iter()
+            .filter(|change| change.impact == Impact::Breaking)
+            .count();
+        writeln!(
+            buffer,
+            "{} -> {}: {} breaking, {} compatible",
+            self.old_version,
+            self.new_version,
+            breaking,
+            self.changes.len() - breaking
+        )?;
+        for change in &self.changes {
+            let impact = match change.impact {
+                Impact::Breaking => "breaking",
+                Impact::Compatible => "compatible",
+            };
+            writeln!(buffer, "{:<10}  {}", impact, change)?;
+        }
+        Ok(buffer)
+    }
+}
+
+fn same_type(old: &SchemaObject, new: &SchemaObject) -> bool {
+    match (&old.id, &new.id) {
+        (Some(old_id), Some(new_id)) => old_id == new_id,
+        _ => SchemaType::from(old).path() == SchemaType::from(new).path(),
+    }
+}
+
+/// The Rust type a reference resolves to, or the reference itself if it
+/// doesn't resolve
+fn resolved(type_ref: &TypeRef, resolver: &Resolver) -> String {
+    match resolver.resolve(type_ref) {
+        Some(schema_type) => schema_type.path(),
+        None => type_ref._ref.clone().unwrap_or_default(),
+    }
+}
+
+/// What a newtype wraps, or nothing for a struct
+fn inner_type(obj: &SchemaObject, resolver: &Resolver) -> String {
+    match obj._type.as_deref() {
+        Some("string") => "String".to_owned(),
+        _ => resolved(&TypeRef::from_ref(obj.type_ref.clone()), resolver),
+    }
+}
+
+fn field_type(prop: &Property, resolver: &Resolver) -> String {
+    let base = resolved(&prop.type_ref, resolver);
+    match prop.array {
+        true => format!("Vec<{}>", base),
+        false => base,
+    }
+}
+
+/// The values in `values` that are not in `other`
+fn missing_from(values: &[String], other: &[String]) -> Vec<String> {
+    values
+        .iter()
+        .filter(|value| !other.contains(value))
+        .cloned()
+        .collect()
+}
+
+/// The properties of an object, keyed by JSON property name rather than by
+/// field name
+fn json_props(obj: &SchemaObject) -> IndexMap<&str, &Property> {
+    obj.props
+        .iter()
+        .flatten()
+        .map(|(_, prop)| (prop.json_name.as_str(), prop))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_schema, Diagnostics, Identifiers, Schema};
+    use serde_json::json;
+
+    fn prop(name: &str, optional: bool, type_ref: &str, enums: &[&str]) -> (String, Property) {
+        let prop = Property {
+            optional,
+            enums: match enums.is_empty() {
+                true => None,
+                false => Some(enums.iter().map(|value| value.to_string()).collect()),
+            },
+            ..Property::test_property(name, name, type_ref)
+        };
+        (name.to_owned(), prop)
+    }
+
+    fn ir(version: &str, objects: Vec<(&str, Vec<(String, Property)>)>) -> Ir {
+        let mut resolver = Resolver::new();
+        resolver.add_type("#token", SchemaType::new("oscal_types", "TokenDatatype"));
+        resolver.add_type("#string", SchemaType::new("oscal_types", "StringDatatype"));
+
+        let mut schema = Schema::new("", version, "");
+        for (name, props) in objects {
+            let obj = SchemaObject {
+                id: Some(format!("#{}", name.to_lowercase())),
+                _type: Some("object".to_owned()),
+                props: Some(IndexMap::from_iter(props)),
+                ..SchemaObject::test_object(&name.to_lowercase(), name)
+            };
+            schema.add_object(&obj.ns, &obj).expect("oops");
+        }
+        Ir { schema, resolver }
+    }
+
+    #[test]
+    fn test_diff() {
+        let old = ir(
+            "1.0.4",
+            vec![
+                (
+                    "Part",
+                    vec![
+                        prop("id", false, "#token", &[]),
+                        prop("name", true, "#token", &["a", "b"]),
+                        prop("class", true, "#token", &[]),
+                        prop("title", true, "#string", &[]),
+                    ],
+                ),
+                ("Gone", vec![]),
+            ],
+        );
+        let new = ir(
+            "1.1.2",
+            vec![
+                (
+                    "Part",
+                    vec![
+                        prop("id", true, "#token", &[]),
+                        prop("name", true, "#token", &["b", "c"]),
+                        prop("title", true, "#token", &[]),
+                        prop("ns", true, "#string", &[]),
+                    ],
+                ),
+                ("Added", vec![]),
+            ],
+        );
+
+        let diff = SchemaDiff::new(&old, &new);
+        let lines: Vec<String> = diff.changes.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            lines,
+            vec![
+                "part::Part: `id` made optional",
+                "part::Part: `name` allows c",
+                "part::Part: `name` no longer allows a",
+                "part::Part: removed `class`",
+                "part::Part: `title` changed from oscal_types::StringDatatype to oscal_types::TokenDatatype",
+                "part::Part: added optional `ns`",
+                "gone::Gone: removed",
+                "added::Added: added",
+            ]
+        );
+        assert!(diff.is_breaking());
+        assert_eq!(diff.changes[1].impact, Impact::Compatible);
+
+        let text = diff.render(DiffFormat::Text).expect("oops");
+        assert!(text.starts_with("1.0.4 -> 1.1.2: 6 breaking, 2 compatible\n"));
+
+        assert!(!SchemaDiff::new(&old, &old).is_breaking());
+    }
+
+    #[test]
+    fn test_fields_by_json_name() {
+        let (_, mut modified) = prop("last_modified", false, "#string", &[]);
+        modified.json_name = "last-modified".to_owned();
+        let old = ir(
+            "1.0.4",
+            vec![("Metadata", vec![("last_modified".to_owned(), modified)])],
+        );
+        // The field name is the same, but serde reads another property
+        let new = ir(
+            "1.1.2",
+            vec![(
+                "Metadata",
+                vec![prop("last_modified", false, "#string", &[])],
+            )],
+        );
+
+        let diff = SchemaDiff::new(&old, &new);
+        let lines: Vec<String> = diff.changes.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            lines,
+            vec![
+                "metadata::Metadata: removed `last-modified`",
+                "metadata::Metadata: added required `last_modified`",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_plain_string_enums() {
+        let parse = |version: &str, values: &[&str]| {
+            let schema = json!({
+                "$schema": "http://json-schema.org/draft-07/schema#",
+                "$id": format!(
+                    "http://csrc.nist.gov/ns/oscal/1.0/{}/oscal-complete-schema.json",
+                    version
+                ),
+                "$comment": "",
+                "definitions": {
+                    "oscal-complete-oscal-catalog:part": {
+                        "$id": "#assembly_oscal-catalog_part",
+                        "type": "object",
+                        "properties": {
+                            "state": { "type": "string", "enum": values }
+                        }
+                    }
+                }
+            });
+            let mut resolver = Resolver::new();
+            let schema = parse_schema(
+                &schema,
+                &mut resolver,
+                &Identifiers::default(),
+                &mut Diagnostics::new(),
+            )
+            .expect("oops");
+            Ir { schema, resolver }
+        };
+
+        let diff = SchemaDiff::new(
+            &parse("1.0.4", &["a", "b"]),
+            &parse("1.1.2", &["a", "b", "c"]),
+        );
+        let lines: Vec<String> = diff.changes.iter().map(ToString::to_string).collect();
+        assert_eq!(lines, vec!["oscal_catalog::part::Part: `state` allows c"]);
+    }
+}
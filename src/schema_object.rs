@@ -1,17 +1,22 @@
 use anyhow::Result;
 use indexmap::IndexMap;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value};
 
 use crate::{try_string_entry, AnyOf, Property, StringType};
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct SchemaObject {
     pub ns: String,
     pub name: String,
+    /// Where the object is in the schema, as a JSON pointer
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pointer: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub id: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none", rename = "type")]
     pub _type: Option<String>,
@@ -26,6 +31,7 @@ pub struct SchemaObject {
 impl SchemaObject {
     pub fn parse(ns: &str, name: &str, map: &Map<String, Value>) -> Result<Self> {
         let id = try_string_entry("$id", map).ok().map(|s| s.to_owned());
+        let title = try_string_entry("title", map).ok().map(|s| s.to_owned());
         let description = try_string_entry("description", map)
             .ok()
             .map(|s| s.to_owned());
@@ -43,7 +49,9 @@ impl SchemaObject {
         Ok(Self {
             ns: ns.to_owned(),
             name: name.to_owned(),
+            pointer: None,
             id,
+            title,
             description,
             _type,
             type_ref,
@@ -52,6 +60,23 @@ impl SchemaObject {
         })
     }
 
+    /// An object with nothing but its name, for tests to fill in
+    #[cfg(test)]
+    pub fn test_object(ns: &str, name: &str) -> Self {
+        Self {
+            ns: ns.to_owned(),
+            name: name.to_owned(),
+            pointer: None,
+            id: None,
+            title: None,
+            description: None,
+            _type: None,
+            type_ref: None,
+            props: None,
+            string_type: None,
+        }
+    }
+
     pub fn has_options(&self) -> bool {
         match &self.props {
             None => false,
@@ -1,20 +1,59 @@
 use crate::SchemaObject;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 /// SchemaType provides the ability to generate Rust `use` statements.
-#[derive(Debug, Clone, PartialEq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct SchemaType {
     pub ns: String,
     pub name: String,
+    /// The type lives outside of the generated crate, such as `uuid::Uuid`.
+    /// External types are imported by their full path, rather than via `crate::`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub external: bool,
 }
 impl SchemaType {
     pub fn new(ns: &str, name: &str) -> Self {
-        if name.contains('_') {
-            eprintln!("SchemaType name should not contain underscores: {}", name);
-        }
         Self {
             ns: ns.to_owned(),
             name: name.to_owned(),
+            external: false,
+        }
+    }
+
+    /// A type outside of the generated crate, given by its Rust path, such as
+    /// `uuid::Uuid` or `chrono::DateTime<chrono::FixedOffset>`.
+    /// Generic arguments stay with the name.
+    pub fn external(path: &str) -> Self {
+        let (base, generics) = match path.find('<') {
+            Some(pos) => path.split_at(pos),
+            None => (path, ""),
+        };
+        let (ns, name) = match base.rfind("::") {
+            Some(pos) => (&base[..pos], &base[pos + 2..]),
+            None => ("", base),
+        };
+        Self {
+            ns: ns.trim_start_matches("::").to_owned(),
+            name: format!("{}{}", name, generics),
+            external: true,
+        }
+    }
+
+    /// The full path, without generic arguments, such as
+    /// `oscal_metadata::metadata::Metadata`.  Identifies the type in a
+    /// [`TypeGraph`](crate::TypeGraph).
+    pub fn path(&self) -> String {
+        match self.ns.is_empty() {
+            true => self.base_name().to_owned(),
+            false => format!("{}::{}", &self.ns, self.base_name()),
+        }
+    }
+
+    /// The name without any generic arguments, as used in `use` statements
+    pub fn base_name(&self) -> &str {
+        match self.name.find('<') {
+            Some(pos) => &self.name[..pos],
+            None => &self.name,
         }
     }
 }
@@ -24,6 +63,24 @@ impl From<&SchemaObject> for SchemaType {
         Self {
             ns: obj.ns.clone(),
             name: obj.name.clone(),
+            external: false,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_external() {
+        let schema_type = SchemaType::external("chrono::DateTime<chrono::FixedOffset>");
+        assert_eq!(schema_type.ns, "chrono");
+        assert_eq!(schema_type.name, "DateTime<chrono::FixedOffset>");
+        assert_eq!(schema_type.base_name(), "DateTime");
+
+        let schema_type = SchemaType::external("u64");
+        assert_eq!(schema_type.ns, "");
+        assert_eq!(schema_type.name, "u64");
+    }
+}
@@ -0,0 +1,86 @@
+//! What a type looks like to serde, to find types that are the same under
+//! different paths, such as across schema versions.
+use crate::{Property, Resolver, SchemaObject, StringType, TypeRef};
+
+/// Everything about a type but its name and docs.  The paths of the types
+/// it refers to are mapped by the caller, such as to make them relative to
+/// a version module.
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct Shape {
+    _type: Option<String>,
+    inner: Option<String>,
+    props: Vec<PropShape>,
+    string_type: Option<StringType>,
+}
+
+#[derive(Debug, PartialEq, Eq, Hash)]
+struct PropShape {
+    json_name: String,
+    name: String,
+    optional: bool,
+    array: bool,
+    target: Option<String>,
+    enums: Option<Vec<String>>,
+}
+
+impl Shape {
+    pub fn new(obj: &SchemaObject, resolver: &Resolver, target: impl Fn(&str) -> String) -> Self {
+        let target = |type_ref: &TypeRef| {
+            resolver
+                .resolve(type_ref)
+                .map(|schema_type| target(&schema_type.path()))
+        };
+        let props = obj
+            .props
+            .iter()
+            .flatten()
+            .map(|(_, prop): (&String, &Property)| PropShape {
+                json_name: prop.json_name.clone(),
+                name: prop.name.clone(),
+                optional: prop.optional,
+                array: prop.array,
+                target: target(&prop.type_ref),
+                enums: prop.enums.clone(),
+            })
+            .collect();
+        Self {
+            _type: obj._type.clone(),
+            inner: target(&TypeRef::from_ref(obj.type_ref.clone())),
+            props,
+            string_type: obj.string_type.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indexmap::IndexMap;
+
+    #[test]
+    fn test_json_names_differ() {
+        let resolver = Resolver::new();
+        let shape = |obj: &SchemaObject| Shape::new(obj, &resolver, str::to_owned);
+        let prop = Property::test_property(
+            "last_modified",
+            "last-modified",
+            "#/definitions/StringDatatype",
+        );
+        let obj = SchemaObject {
+            _type: Some("object".to_owned()),
+            props: Some(IndexMap::from([("last_modified".to_owned(), prop.clone())])),
+            ..SchemaObject::test_object("metadata", "Metadata")
+        };
+        // The same field, read from another property by serde
+        let renamed = Property {
+            json_name: "last_modified".to_owned(),
+            ..prop
+        };
+        let other = SchemaObject {
+            props: Some(IndexMap::from([("last_modified".to_owned(), renamed)])),
+            ..obj.clone()
+        };
+        assert_ne!(shape(&obj), shape(&other));
+        assert_eq!(shape(&obj), shape(&obj.clone()));
+    }
+}
@@ -1,11 +1,11 @@
 use anyhow::Result;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value};
 
 use crate::try_string_entry;
 
 /// NumberType represents a constraint on the IntegrDatatype
-#[derive(Debug, Clone, PartialEq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct StringType {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub format: Option<String>,
@@ -20,11 +20,12 @@ impl StringType {
         self.format.is_none() && self.pattern.is_none() && self.content_encoding.is_none()
     }
 
+    /// The string constraints in `map`, if it has any
     pub fn parse(map: &Map<String, Value>) -> Result<Option<Self>> {
         let result = Self::from(map);
         match result.is_empty() {
-            true => Ok(Some(result)),
-            false => Ok(None),
+            true => Ok(None),
+            false => Ok(Some(result)),
         }
     }
 }
@@ -1,5 +1,6 @@
 #![allow(dead_code)]
 use anyhow::Result;
+use serde::{Deserialize, Serialize};
 
 use crate::{Schema, SchemaObject};
 
@@ -10,9 +11,11 @@ enum Error {
     #[error("Not a tree")]
     NotTree,
 }
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum TreeEntry {
     Object(Box<SchemaObject>),
-    Tree(Schema),
+    Tree(Box<Schema>),
 }
 
 impl TreeEntry {
@@ -0,0 +1,525 @@
+//! The graph of generated types, and the types their fields refer to.
+//!
+//! Nodes are the [`SchemaObject`]s in the [`Schema`] tree, keyed by their
+//! Rust path.  Edges are the properties, resolved through the [`Resolver`].
+//! Edge targets that are not nodes, such as `String` or `oscal_types`
+//! datatypes, are leaves.
+//!
+//! Types that hold each other by value, rather than through a `Vec`, would be
+//! infinitely sized.  [`TypeGraph::break_cycles`] boxes fields until no such
+//! cycle is left.
+use anyhow::Result;
+use indexmap::IndexMap;
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{Error, Resolver, Schema, SchemaObject, SchemaType, TypeRef};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeEdge {
+    /// The field name, or `None` for the inner type of a newtype
+    pub prop: Option<String>,
+    /// The JSON property name of the field
+    pub json_name: Option<String>,
+    /// The path of the referenced type
+    pub target: String,
+    pub optional: bool,
+    pub array: bool,
+    /// The field is a `Box`, to break a cycle
+    pub boxed: bool,
+}
+
+impl TypeEdge {
+    /// Is the target held by value, making it part of the size of the type
+    fn is_sized(&self) -> bool {
+        !self.array && !self.boxed
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeNode {
+    pub schema_type: SchemaType,
+    /// Where the type is in the schema, as a JSON pointer
+    pub pointer: String,
+    pub edges: Vec<TypeEdge>,
+}
+
+/// Types that hold each other by value, and the fields boxed to break the
+/// cycle
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cycle {
+    pub types: Vec<String>,
+    /// The type and field name of every boxed field
+    pub boxed: Vec<(String, String)>,
+    /// Where the type of the first boxed field is in the schema
+    pub pointer: String,
+}
+
+impl std::fmt::Display for Cycle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let boxed = self
+            .boxed
+            .iter()
+            .map(|(path, prop)| format!("{}.{}", path, prop))
+            .collect::<Vec<String>>();
+        write!(
+            f,
+            "Recursive types {}: boxed {}",
+            self.types.join(", "),
+            boxed.join(", ")
+        )
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TypeGraph {
+    nodes: IndexMap<String, TypeNode>,
+}
+
+impl TypeGraph {
+    /// Build the graph from every object in the tree.  Properties that don't
+    /// resolve are left out.
+    pub fn build(schema: &Schema, resolver: &Resolver) -> Self {
+        let mut result = Self::default();
+        for obj in schema.objects() {
+            result.add_object(obj, resolver);
+        }
+        result
+    }
+
+    pub fn add_object(&mut self, obj: &SchemaObject, resolver: &Resolver) {
+        let schema_type = SchemaType::from(obj);
+        let mut edges = vec![];
+
+        if obj._type == Some("string".to_owned()) {
+            edges.push(TypeEdge {
+                prop: None,
+                json_name: None,
+                target: "String".to_owned(),
+                optional: false,
+                array: false,
+                boxed: false,
+            });
+        } else if let Some(target) = resolver.resolve(&TypeRef::from_ref(obj.type_ref.clone())) {
+            edges.push(TypeEdge {
+                prop: None,
+                json_name: None,
+                target: target.path(),
+                optional: false,
+                array: false,
+                boxed: false,
+            });
+        }
+
+        if let Some(props) = &obj.props {
+            for (prop_name, prop) in props {
+                let Some(target) = resolver.resolve(&prop.type_ref) else {
+                    continue;
+                };
+                edges.push(TypeEdge {
+                    prop: Some(prop_name.to_owned()),
+                    json_name: Some(prop.json_name.clone()),
+                    target: target.path(),
+                    optional: prop.optional,
+                    array: prop.array,
+                    boxed: false,
+                });
+            }
+        }
+
+        let node = TypeNode {
+            schema_type,
+            pointer: obj.pointer.clone().unwrap_or_default(),
+            edges,
+        };
+        self.nodes.insert(node.schema_type.path(), node);
+    }
+
+    /// Point the edges to the types in `aliases`, keyed by path, to the
+    /// types they alias, so that cycles are found through the generated type
+    pub fn redirect(&mut self, aliases: &IndexMap<String, String>) {
+        for edge in self
+            .nodes
+            .values_mut()
+            .flat_map(|node| node.edges.iter_mut())
+        {
+            if let Some(target) = aliases.get(&edge.target) {
+                edge.target = target.to_owned();
+            }
+        }
+    }
+
+    pub fn get(&self, path: &str) -> Option<&TypeNode> {
+        self.nodes.get(path)
+    }
+
+    pub fn contains(&self, path: &str) -> bool {
+        self.nodes.contains_key(path)
+    }
+
+    pub fn iter(&self) -> indexmap::map::Iter<'_, String, TypeNode> {
+        self.nodes.iter()
+    }
+
+    /// Is the field `prop` of the type at `path` boxed
+    pub fn is_boxed(&self, path: &str, prop: &str) -> bool {
+        self.get(path)
+            .map(|node| {
+                node.edges
+                    .iter()
+                    .any(|edge| edge.boxed && edge.prop.as_deref() == Some(prop))
+            })
+            .unwrap_or(false)
+    }
+
+    /// Box fields until no type holds itself by value, directly or not.
+    /// For each cycle, the smallest set of fields that breaks it is boxed,
+    /// preferring fields earlier in graph order.  Newtypes can't be boxed.
+    /// Cycles too large to search are broken one at a time instead, boxing
+    /// the last field that closes each one.  A cycle through newtypes only
+    /// can't be broken, and is an error.
+    pub fn break_cycles(&mut self) -> Result<Vec<Cycle>> {
+        let mut result = vec![];
+        for types in self.sized_components() {
+            if let Some(boxed) = self.smallest_boxing(&types) {
+                for (path, index) in &boxed {
+                    self.nodes[path].edges[*index].boxed = true;
+                }
+                let boxed = boxed
+                    .into_iter()
+                    .map(|(path, index)| {
+                        let prop = self.nodes[&path].edges[index].prop.clone();
+                        (path, prop.unwrap_or_default())
+                    })
+                    .collect();
+                result.push(self.cycle(types, boxed));
+                continue;
+            }
+
+            let mut boxed = vec![];
+            while let Some(cycle) = self.find_cycle(&types) {
+                let Some((path, index)) = cycle
+                    .into_iter()
+                    .rev()
+                    .find(|(path, index)| self.nodes[path].edges[*index].prop.is_some())
+                else {
+                    return Err(Error::NewtypeCycle(types.join(", ")).into());
+                };
+                let edge = &mut self.nodes[&path].edges[index];
+                edge.boxed = true;
+                boxed.push((path, edge.prop.clone().unwrap_or_default()));
+            }
+            result.push(self.cycle(types, boxed));
+        }
+        Ok(result)
+    }
+
+    fn cycle(&self, types: Vec<String>, boxed: Vec<(String, String)>) -> Cycle {
+        let pointer = boxed
+            .first()
+            .and_then(|(path, _)| self.nodes.get(path))
+            .map(|node| node.pointer.clone())
+            .unwrap_or_default();
+        Cycle {
+            types,
+            boxed,
+            pointer,
+        }
+    }
+
+    /// The smallest set of fields within `types` that, boxed, leaves no
+    /// cycle.  `None` if there are too many fields to search, or if no set
+    /// of fields will do.
+    fn smallest_boxing(&mut self, types: &[String]) -> Option<Vec<(String, usize)>> {
+        let candidates = types
+            .iter()
+            .flat_map(|path| {
+                self.sized_edges(path)
+                    .filter(|(_, edge)| edge.prop.is_some() && types.contains(&edge.target))
+                    .map(|(index, _)| (path.to_owned(), index))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<(String, usize)>>();
+        if candidates.len() > MAX_BOXING_SEARCH {
+            return None;
+        }
+
+        for size in 1..=candidates.len() {
+            let mut found = None;
+            for_each_combination(candidates.len(), size, &mut |combination| {
+                if found.is_some() {
+                    return;
+                }
+                let boxed = combination
+                    .iter()
+                    .map(|i| candidates[*i].clone())
+                    .collect::<Vec<_>>();
+                for (path, index) in &boxed {
+                    self.nodes[path].edges[*index].boxed = true;
+                }
+                if self.find_cycle(types).is_none() {
+                    found = Some(boxed.clone());
+                }
+                for (path, index) in &boxed {
+                    self.nodes[path].edges[*index].boxed = false;
+                }
+            });
+            if found.is_some() {
+                return found;
+            }
+        }
+        None
+    }
+
+    /// The edges of a type that are held by value, and lead to another type
+    fn sized_edges<'a>(&'a self, path: &str) -> impl Iterator<Item = (usize, &'a TypeEdge)> + 'a {
+        self.type_edges(path, true)
+    }
+
+    /// The edges of a type that lead to another type, rather than to a leaf
+    fn type_edges<'a>(
+        &'a self,
+        path: &str,
+        sized_only: bool,
+    ) -> impl Iterator<Item = (usize, &'a TypeEdge)> + 'a {
+        self.nodes
+            .get(path)
+            .into_iter()
+            .flat_map(|node| node.edges.iter().enumerate())
+            .filter(move |(_, edge)| {
+                (!sized_only || edge.is_sized()) && self.nodes.contains_key(&edge.target)
+            })
+    }
+
+    /// The cycles of types held by value.  See [`Self::components`].
+    pub fn sized_components(&self) -> Vec<Vec<String>> {
+        self.components(true)
+    }
+
+    /// The cycles of types that refer to each other, by value or not, such
+    /// as a `Part` with `parts`.  See [`Self::components`].
+    pub fn recursive_components(&self) -> Vec<Vec<String>> {
+        self.components(false)
+    }
+
+    /// The strongly connected components of the graph, or of the types held
+    /// by value, using Tarjan's algorithm.  Only components that are cycles
+    /// are returned, with their types in graph order.
+    fn components(&self, sized_only: bool) -> Vec<Vec<String>> {
+        let mut tarjan = Tarjan {
+            sized_only,
+            ..Default::default()
+        };
+        for path in self.nodes.keys() {
+            if !tarjan.indices.contains_key(path) {
+                tarjan.connect(self, path);
+            }
+        }
+
+        tarjan
+            .components
+            .into_iter()
+            .filter(|component| {
+                component.len() > 1
+                    || self
+                        .type_edges(&component[0], sized_only)
+                        .any(|(_, edge)| edge.target == component[0])
+            })
+            .map(|component| {
+                let mut component = component;
+                component.sort_by_key(|path| self.nodes.get_index_of(path));
+                component
+            })
+            .collect()
+    }
+
+    /// A cycle of types held by value, within `types`, as the type and
+    /// edge index of every step
+    fn find_cycle(&self, types: &[String]) -> Option<Vec<(String, usize)>> {
+        let mut done = BTreeSet::new();
+        types
+            .iter()
+            .find_map(|path| self.cycle_from(path, types, &mut vec![], &mut done))
+    }
+
+    fn cycle_from(
+        &self,
+        path: &str,
+        types: &[String],
+        steps: &mut Vec<(String, usize)>,
+        done: &mut BTreeSet<String>,
+    ) -> Option<Vec<(String, usize)>> {
+        if done.contains(path) {
+            return None;
+        }
+        for (index, edge) in self.sized_edges(path) {
+            if !types.contains(&edge.target) {
+                continue;
+            }
+            if edge.target == path {
+                return Some(vec![(path.to_owned(), index)]);
+            }
+            if let Some(start) = steps.iter().position(|(step, _)| *step == edge.target) {
+                let mut cycle = steps[start..].to_vec();
+                cycle.push((path.to_owned(), index));
+                return Some(cycle);
+            }
+            steps.push((path.to_owned(), index));
+            if let Some(cycle) = self.cycle_from(&edge.target, types, steps, done) {
+                return Some(cycle);
+            }
+            steps.pop();
+        }
+        done.insert(path.to_owned());
+        None
+    }
+}
+
+/// The most fields in a cycle for which the smallest set to box is
+/// searched for
+const MAX_BOXING_SEARCH: usize = 16;
+
+/// Call `f` with every combination of `size` of the indices `0..len`, in
+/// lexicographic order
+fn for_each_combination(len: usize, size: usize, f: &mut dyn FnMut(&[usize])) {
+    fn extend(
+        start: usize,
+        len: usize,
+        size: usize,
+        picked: &mut Vec<usize>,
+        f: &mut dyn FnMut(&[usize]),
+    ) {
+        if picked.len() == size {
+            f(picked);
+            return;
+        }
+        for i in start..len {
+            picked.push(i);
+            extend(i + 1, len, size, picked, f);
+            picked.pop();
+        }
+    }
+    extend(0, len, size, &mut vec![], f);
+}
+
+#[derive(Default)]
+struct Tarjan {
+    /// Only follow edges held by value
+    sized_only: bool,
+    next: usize,
+    indices: BTreeMap<String, usize>,
+    lowlinks: BTreeMap<String, usize>,
+    stack: Vec<String>,
+    components: Vec<Vec<String>>,
+}
+
+impl Tarjan {
+    fn connect(&mut self, graph: &TypeGraph, path: &str) {
+        self.indices.insert(path.to_owned(), self.next);
+        self.lowlinks.insert(path.to_owned(), self.next);
+        self.next += 1;
+        self.stack.push(path.to_owned());
+
+        for (_, edge) in graph.type_edges(path, self.sized_only) {
+            let target = &edge.target;
+            if !self.indices.contains_key(target) {
+                self.connect(graph, target);
+                let lowlink = self.lowlinks[path].min(self.lowlinks[target]);
+                self.lowlinks.insert(path.to_owned(), lowlink);
+            } else if self.stack.contains(target) {
+                let lowlink = self.lowlinks[path].min(self.indices[target]);
+                self.lowlinks.insert(path.to_owned(), lowlink);
+            }
+        }
+
+        if self.lowlinks[path] == self.indices[path] {
+            let mut component = vec![];
+            while let Some(member) = self.stack.pop() {
+                let done = member == path;
+                component.push(member);
+                if done {
+                    break;
+                }
+            }
+            self.components.push(component);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(path: &str, edges: &[(&str, &str, bool)]) -> (String, TypeNode) {
+        let edges = edges
+            .iter()
+            .map(|(prop, target, array)| TypeEdge {
+                prop: Some(prop.to_string()),
+                json_name: Some(prop.to_string()),
+                target: target.to_string(),
+                optional: true,
+                array: *array,
+                boxed: false,
+            })
+            .collect();
+        (
+            path.to_owned(),
+            TypeNode {
+                schema_type: SchemaType::new("", path),
+                pointer: format!("/definitions/{}", path),
+                edges,
+            },
+        )
+    }
+
+    #[test]
+    fn test_break_cycles() {
+        let mut graph = TypeGraph {
+            nodes: IndexMap::from([
+                // Recursion through a Vec needs no Box
+                node("Part", &[("parts", "Part", true)]),
+                node("Node", &[("next", "Node", false)]),
+                node("A", &[("b", "B", false), ("name", "String", false)]),
+                node("B", &[("c", "C", false)]),
+                node("C", &[("a", "A", false), ("b", "B", false)]),
+            ]),
+        };
+
+        assert_eq!(
+            graph.sized_components(),
+            vec![
+                vec!["Node".to_owned()],
+                vec!["A".to_owned(), "B".to_owned(), "C".to_owned()]
+            ]
+        );
+
+        let cycles = graph.break_cycles().expect("oops");
+        assert_eq!(
+            cycles[0].boxed,
+            vec![("Node".to_owned(), "next".to_owned())]
+        );
+        // Boxing B.c breaks both A -> B -> C -> A and B -> C -> B
+        assert_eq!(cycles[1].boxed, vec![("B".to_owned(), "c".to_owned())]);
+        assert_eq!(cycles[1].pointer, "/definitions/B");
+        assert!(graph.sized_components().is_empty());
+        assert_eq!(graph.recursive_components().len(), 3);
+        assert!(!graph.is_boxed("Part", "parts"));
+        assert!(graph.is_boxed("Node", "next"));
+    }
+
+    #[test]
+    fn test_newtype_cycle() {
+        let newtype = |path: &str, target: &str| {
+            let (path, mut node) = node(path, &[("", target, false)]);
+            node.edges[0].prop = None;
+            (path, node)
+        };
+        let mut graph = TypeGraph {
+            nodes: IndexMap::from([
+                node("Holder", &[("id", "Id", false)]),
+                newtype("Id", "Alias"),
+                newtype("Alias", "Id"),
+            ]),
+        };
+        assert!(graph.break_cycles().is_err());
+    }
+}
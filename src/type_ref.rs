@@ -1,8 +1,8 @@
 use super::SchemaType;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 /// TypeRef enables eventual resolution of schema `$ref` to Rust types.
-#[derive(Debug, Clone, PartialEq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct TypeRef {
     #[serde(skip_serializing_if = "Option::is_none", rename = "type")]
     pub _type: Option<SchemaType>,
@@ -24,10 +24,7 @@ impl TypeRef {
 
     pub fn native_string() -> Self {
         Self {
-            _type: Some(SchemaType {
-                ns: "".to_owned(),
-                name: "String".to_owned(),
-            }),
+            _type: Some(SchemaType::new("", "String")),
             _ref: None,
         }
     }
@@ -0,0 +1,32 @@
+use sha2::{Digest, Sha256};
+
+/// The generator version, which is part of every fingerprint.  Output from
+/// the same inputs changes whenever the generator does.
+pub static GENERATOR_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// SHA-256 over the generator version and the content of every input, as a
+/// lower case hex string.
+pub fn fingerprint(inputs: &[&[u8]]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(format!("oscal_codegen {}\n", GENERATOR_VERSION));
+    for input in inputs {
+        // Length prefix, so that [ab, c] and [a, bc] don't collide
+        hasher.update((input.len() as u64).to_le_bytes());
+        hasher.update(input);
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fingerprint_is_stable() {
+        let first = fingerprint(&[b"{}"]);
+        assert_eq!(first, fingerprint(&[b"{}"]));
+        assert_eq!(first.len(), 64);
+        assert_ne!(first, fingerprint(&[b"{ }"]));
+        assert_ne!(fingerprint(&[b"ab", b"c"]), fingerprint(&[b"a", b"bc"]));
+    }
+}
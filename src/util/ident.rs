@@ -0,0 +1,158 @@
+//! Turning schema names into valid Rust identifiers.
+//!
+//! Characters that can't appear in an identifier become `_`, and names that
+//! start with a digit get a leading `_`.  Keywords either become raw
+//! identifiers, such as `r#type`, or get a suffix, such as `type_`.  Module
+//! and type names always get the suffix, since module names are also file
+//! names, and `self`, `super`, `crate` and `Self` can't be raw.
+use convert_case::{Case, Casing};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+
+use super::UnPlural;
+
+/// Every strict and reserved keyword, in any edition
+pub static RUST_KEYWORDS: [&str; 52] = [
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate",
+    "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl",
+    "in", "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+    "return", "self", "Self", "static", "struct", "super", "trait", "true", "try", "type",
+    "typeof", "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+/// Keywords that can't be raw identifiers
+static NOT_RAW: [&str; 4] = ["crate", "self", "Self", "super"];
+
+/// How schema names are made into identifiers
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Identifiers {
+    /// Fields named after a keyword become raw identifiers, such as
+    /// `r#type`.  Otherwise they get the suffix.
+    pub raw: bool,
+    /// Appended to keywords that aren't raw identifiers
+    pub suffix: String,
+    /// Singulars for plural names the inflection rules get wrong, used to
+    /// name array item types.  Such as `{ data = "datum" }`.
+    pub singulars: IndexMap<String, String>,
+    /// Names for generated modules, keyed by the module path they would
+    /// otherwise have, such as `{ "oscal_catalog::group::part" = "group_part" }`.
+    /// Types in renamed modules are named after the module.
+    pub renames: IndexMap<String, String>,
+}
+
+impl Default for Identifiers {
+    fn default() -> Self {
+        Self {
+            raw: true,
+            suffix: "_".to_owned(),
+            singulars: IndexMap::new(),
+            renames: IndexMap::new(),
+        }
+    }
+}
+
+impl Identifiers {
+    /// The snake_case field name for a JSON property name
+    pub fn field(&self, json_name: &str) -> String {
+        self.sanitize(&clean(json_name).to_case(Case::Snake), self.raw)
+    }
+
+    /// A module name, which is also a file name
+    pub fn module(&self, name: &str) -> String {
+        self.sanitize(&clean(name), false)
+    }
+
+    /// A type, or enum variant, name
+    pub fn type_name(&self, name: &str) -> String {
+        self.sanitize(&clean(name), false)
+    }
+
+    /// The singular of a snake_case name, from the config or the inflection
+    /// rules
+    pub fn singular(&self, name: &str) -> String {
+        match self.singulars.get(name) {
+            Some(singular) => singular.to_owned(),
+            None => name.unplural(),
+        }
+    }
+
+    fn sanitize(&self, name: &str, raw: bool) -> String {
+        let name = match name.chars().next() {
+            // Nothing, or only `_`, is left of names made of symbols
+            _ if name.chars().all(|c| c == '_') => "_unnamed".to_owned(),
+            Some(c) if c.is_ascii_digit() => format!("_{}", name),
+            _ => name.to_owned(),
+        };
+        if !RUST_KEYWORDS.contains(&name.as_str()) {
+            return name;
+        }
+        match raw && !NOT_RAW.contains(&name.as_str()) {
+            true => format!("r#{}", name),
+            false => format!("{}{}", name, self.suffix),
+        }
+    }
+}
+
+/// Replace everything that can't be part of an identifier with `_`
+fn clean(name: &str) -> String {
+    name.chars()
+        .map(|c| match c.is_ascii_alphanumeric() || c == '_' {
+            true => c,
+            false => '_',
+        })
+        .collect()
+}
+
+/// The identifier without any `r#`, as serde sees it
+pub fn unraw(ident: &str) -> &str {
+    ident.strip_prefix("r#").unwrap_or(ident)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_field() {
+        let idents = Identifiers::default();
+        assert_eq!(idents.field("last-modified"), "last_modified");
+        assert_eq!(idents.field("type"), "r#type");
+        assert_eq!(idents.field("self"), "self_");
+        assert_eq!(idents.field("async"), "r#async");
+        assert_eq!(idents.field("1st-party"), "_1_st_party");
+        assert_eq!(idents.field("a.b"), "a_b");
+
+        let idents = Identifiers {
+            raw: false,
+            suffix: "_field".to_owned(),
+            ..Default::default()
+        };
+        assert_eq!(idents.field("match"), "match_field");
+    }
+
+    #[test]
+    fn test_module_and_type() {
+        let idents = Identifiers::default();
+        assert_eq!(idents.module("mod"), "mod_");
+        assert_eq!(idents.module("use"), "use_");
+        assert_eq!(idents.module("oscal_catalog"), "oscal_catalog");
+        assert_eq!(idents.type_name("Self"), "Self_");
+        assert_eq!(idents.type_name("Catalog"), "Catalog");
+        assert_eq!(idents.type_name("9Lives"), "_9Lives");
+        assert_eq!(idents.type_name("$"), "_unnamed");
+        assert_eq!(idents.type_name("-"), "_unnamed");
+        assert_eq!(idents.module("$-"), "_unnamed");
+        assert_eq!(idents.field("$"), "_unnamed");
+    }
+
+    #[test]
+    fn test_singular() {
+        let idents = Identifiers {
+            singulars: IndexMap::from([("data".to_owned(), "datum".to_owned())]),
+            ..Default::default()
+        };
+        assert_eq!(idents.singular("data"), "datum");
+        assert_eq!(idents.singular("statuses"), "status");
+    }
+}
@@ -1,6 +1,10 @@
 #![allow(unused_imports)]
+pub use fingerprint::*;
+pub use ident::*;
 pub use names::*;
 pub use value::*;
 
+pub mod fingerprint;
+pub mod ident;
 pub mod names;
 pub mod value;
@@ -41,7 +41,10 @@
 /// `oscal_ap::assessment_plan::AssessmentPlan`
 use convert_case::{Case, Casing};
 
-pub static OSCAL_COMPLETE: &str = "oscal-complete-";
+/// Definitions of model schemas start with `oscal-<model>-`, such as
+/// `oscal-complete-` or `oscal-catalog-`, followed by their namespace
+pub static OSCAL_PREFIX: &str = "oscal-";
+static OSCAL_NAMESPACE: &str = "-oscal-";
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct NamespaceEntry {
@@ -75,29 +78,126 @@ pub fn name_parts(name: &str) -> Vec<&str> {
     names
 }
 
-/// Strip off the starting oscal-complete-oscal-
+/// Strip off the starting model name, such as `oscal-complete-` or
+/// `oscal-catalog-`
 pub fn strip_oscal(name: &str) -> &str {
-    if !name.starts_with(OSCAL_COMPLETE) {
-        return name;
+    match model_len(name) {
+        Some(len) => &name[len..],
+        None => name,
     }
-    &name[OSCAL_COMPLETE.len()..]
+}
+
+/// The length of the `oscal-<model>-` at the start of `name`, up to the
+/// first `-oscal-` before the ':'
+fn model_len(name: &str) -> Option<usize> {
+    if !name.starts_with(OSCAL_PREFIX) {
+        return None;
+    }
+    let namespace = name.split(':').next().unwrap_or(name);
+    namespace.find(OSCAL_NAMESPACE).map(|pos| pos + 1)
 }
 
 fn is_namespace(name: &str) -> bool {
-    name.starts_with(OSCAL_COMPLETE) && name.contains(':')
+    model_len(name).is_some() && name.contains(':')
 }
 
+/// Words that are the same in the singular and plural
+static UNCOUNTABLES: [&str; 10] = [
+    "data",
+    "equipment",
+    "evidence",
+    "hardware",
+    "information",
+    "metadata",
+    "news",
+    "series",
+    "software",
+    "species",
+];
+
+/// Plurals that don't follow the rules, and their singulars
+static IRREGULARS: [(&str, &str); 10] = [
+    ("analyses", "analysis"),
+    ("appendices", "appendix"),
+    ("children", "child"),
+    ("criteria", "criterion"),
+    ("indices", "index"),
+    ("matrices", "matrix"),
+    ("men", "man"),
+    ("people", "person"),
+    ("vertices", "vertex"),
+    ("women", "woman"),
+];
+
+/// Endings of singular words that end in `s`, such as `status`
+static SINGULAR_ENDINGS: [&str; 3] = ["ss", "us", "is"];
+
+/// Endings of plurals that are the singular plus `es`, such as `statuses`
+static ES_ENDINGS: [&str; 5] = ["sses", "uses", "xes", "ches", "shes"];
+
+/// Singulars ending in `e` with a plural ending in one of the above, such as
+/// `causes`
+static E_SINGULARS: [&str; 16] = [
+    "abuse",
+    "ache",
+    "avalanche",
+    "cache",
+    "cause",
+    "clause",
+    "cliche",
+    "excuse",
+    "fuse",
+    "house",
+    "misuse",
+    "niche",
+    "pause",
+    "psyche",
+    "refuse",
+    "use",
+];
+
+/// The singular of an English noun.  For snake_case names, only the last
+/// word changes, so `assessment_assets` becomes `assessment_asset`.
 pub trait UnPlural {
     fn unplural(&self) -> String;
 }
 
 impl UnPlural for &str {
     fn unplural(&self) -> String {
-        match self.chars().nth(self.len() - 1) {
-            Some('s') => self[..self.len() - 1].to_string(),
-            _ => self.to_string(),
+        let (head, word) = match self.rfind('_') {
+            Some(pos) => self.split_at(pos + 1),
+            None => ("", *self),
+        };
+        format!("{}{}", head, singular_word(word))
+    }
+}
+
+fn singular_word(word: &str) -> String {
+    if UNCOUNTABLES.contains(&word) {
+        return word.to_owned();
+    }
+    if let Some((_, singular)) = IRREGULARS.iter().find(|(plural, _)| *plural == word) {
+        return singular.to_string();
+    }
+    if let Some(stem) = word.strip_suffix("ies") {
+        if !stem.is_empty() {
+            return format!("{}y", stem);
         }
     }
+    if ES_ENDINGS.iter().any(|ending| word.ends_with(ending)) {
+        let singular = &word[..word.len() - 1];
+        return match E_SINGULARS.contains(&singular) {
+            true => singular.to_owned(),
+            false => singular[..singular.len() - 1].to_owned(),
+        };
+    }
+    if SINGULAR_ENDINGS.iter().any(|ending| word.ends_with(ending)) {
+        return word.to_owned();
+    }
+    match word.strip_suffix('s') {
+        Some(stem) if !stem.is_empty() => stem.to_owned(),
+        _ => word.to_owned(),
+    }
 }
 
 #[cfg(test)]
@@ -110,13 +210,40 @@ mod tests {
             "assessment_assets".unplural(),
             "assessment_asset".to_owned()
         );
+        for (plural, singular) in [
+            ("properties", "property"),
+            ("statuses", "status"),
+            ("status", "status"),
+            ("addresses", "address"),
+            ("address", "address"),
+            ("responses", "response"),
+            ("boxes", "box"),
+            ("hashes", "hash"),
+            ("matches", "match"),
+            ("campuses", "campus"),
+            ("causes", "cause"),
+            ("uses", "use"),
+            ("sizes", "size"),
+            ("caches", "cache"),
+            ("niches", "niche"),
+            ("phases", "phase"),
+            ("series", "series"),
+            ("people", "person"),
+            ("test_criteria", "test_criterion"),
+            ("s", "s"),
+            ("", ""),
+        ] {
+            assert_eq!(plural.unplural(), singular);
+        }
     }
 
     #[test]
     fn test_is_namespace() {
         assert!(is_namespace("oscal-complete-oscal-ap:assessment-plan"));
+        assert!(is_namespace("oscal-catalog-oscal-catalog:catalog"));
 
         assert!(!is_namespace("StringDatatype"));
+        assert!(!is_namespace("oscal-catalog:catalog"));
     }
 
     #[test]
@@ -125,6 +252,15 @@ mod tests {
             strip_oscal("oscal-complete-oscal-ap:assessment-plan"),
             "oscal-ap:assessment-plan"
         );
+        assert_eq!(
+            strip_oscal("oscal-ap-oscal-ap:assessment-plan"),
+            "oscal-ap:assessment-plan"
+        );
+        assert_eq!(
+            strip_oscal("oscal-catalog-oscal-catalog:catalog"),
+            "oscal-catalog:catalog"
+        );
+        assert_eq!(strip_oscal("StringDatatype"), "StringDatatype");
     }
 
     #[test]
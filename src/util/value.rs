@@ -30,7 +30,7 @@ pub fn value_to_string(value: &Value) -> Result<&String> {
 pub fn value_to_map(value: &Value) -> Result<&Map<String, Value>> {
     match value {
         Value::Object(s) => Ok(s),
-        _ => Err(Error::StringExpected.into()),
+        _ => Err(Error::ObjectExpected.into()),
     }
 }
 
@@ -38,7 +38,7 @@ pub fn value_to_map(value: &Value) -> Result<&Map<String, Value>> {
 pub fn value_to_array(value: &Value) -> Result<&Vec<Value>> {
     match value {
         Value::Array(s) => Ok(s),
-        _ => Err(Error::StringExpected.into()),
+        _ => Err(Error::ArrayExpected.into()),
     }
 }
 
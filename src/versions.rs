@@ -0,0 +1,293 @@
+//! Several OSCAL versions in one crate.
+//!
+//! The tree of each version becomes a module named after the version, such
+//! as `v1_0_4`.  Type paths and `$ref`s are prefixed with that module, so
+//! the versions share a single [`Resolver`].  Types that didn't change from
+//! one version to the next are generated once, and re-exported by the later
+//! versions.
+use anyhow::Result;
+use indexmap::IndexMap;
+
+use crate::{Error, Ir, Resolver, Schema, SchemaObject, SchemaType, Shape, TypeRef};
+
+/// The module for an OSCAL version, such as `v1_0_4` for `1.0.4`
+pub fn version_module(version: &str) -> String {
+    format!("v{}", version.replace(['.', '-'], "_"))
+}
+
+/// Merge the IRs of several schema versions into one, in the order given.
+/// The crate level version is that of the last.
+pub fn merge_versions(irs: Vec<Ir>) -> Result<Ir> {
+    let Some(last) = irs.last() else {
+        return Err(Error::NoDefinitions.into());
+    };
+    let mut schema = Schema::new(
+        &last.schema.schema,
+        &last.schema.version,
+        &last.schema.comment,
+    );
+    let mut resolver = Resolver::new();
+
+    for Ir {
+        schema: mut tree,
+        resolver: tree_resolver,
+    } in irs
+    {
+        let module = version_module(&tree.version);
+        if schema.has(&module) {
+            return Err(Error::DuplicateVersion(tree.version.clone()).into());
+        }
+
+        for obj in tree.objects_mut() {
+            obj.ns = prefix_ns(&module, &obj.ns);
+            obj.type_ref = obj.type_ref.as_ref().map(|_ref| prefix_ref(&module, _ref));
+            for prop in obj.props.iter_mut().flat_map(|props| props.values_mut()) {
+                prop.type_ref = prefix_type_ref(&module, &prop.type_ref);
+            }
+        }
+        for type_ref in tree.documents.values_mut() {
+            *type_ref = prefix_type_ref(&module, type_ref);
+        }
+        for (_ref, schema_type) in tree_resolver.iter() {
+            resolver.add_type(
+                &prefix_ref(&module, _ref),
+                prefix_type(&module, schema_type),
+            );
+        }
+
+        schema.insert_tree(&module, tree);
+    }
+
+    Ok(Ir { schema, resolver })
+}
+
+fn prefix_ns(module: &str, ns: &str) -> String {
+    match ns {
+        "" | "oscal_types" => ns.to_owned(),
+        _ => format!("{}::{}", module, ns),
+    }
+}
+
+fn prefix_ref(module: &str, _ref: &str) -> String {
+    format!("{}{}", module, _ref)
+}
+
+fn prefix_type(module: &str, schema_type: &SchemaType) -> SchemaType {
+    match schema_type.external {
+        true => schema_type.clone(),
+        false => SchemaType {
+            ns: prefix_ns(module, &schema_type.ns),
+            ..schema_type.clone()
+        },
+    }
+}
+
+fn prefix_type_ref(module: &str, type_ref: &TypeRef) -> TypeRef {
+    TypeRef {
+        _type: type_ref
+            ._type
+            .as_ref()
+            .map(|schema_type| prefix_type(module, schema_type)),
+        _ref: type_ref._ref.as_ref().map(|_ref| prefix_ref(module, _ref)),
+    }
+}
+
+/// A type path without its version module
+pub fn unprefixed<'a>(module: &str, path: &'a str) -> &'a str {
+    path.strip_prefix(module)
+        .and_then(|path| path.strip_prefix("::"))
+        .unwrap_or(path)
+}
+
+fn relative_path(module: &str, obj: &SchemaObject) -> String {
+    unprefixed(module, &SchemaType::from(obj).path()).to_owned()
+}
+
+/// The types of each version that are the same as in the previous version,
+/// keyed by path, with the path of the type they re-export.  A type is only
+/// the same if every generated type it refers to is too.
+pub fn shared_types(tree: &Schema, resolver: &Resolver) -> IndexMap<String, String> {
+    let mut result = IndexMap::new();
+    let versions = tree.version_trees();
+
+    for pair in versions.windows(2) {
+        let [(prev_module, prev_tree), (module, version_tree)] = pair else {
+            continue;
+        };
+        let previous: IndexMap<String, &SchemaObject> = prev_tree
+            .objects()
+            .into_iter()
+            .map(|obj| (relative_path(prev_module, obj), obj))
+            .collect();
+
+        // Types that look the same, with the type they re-export
+        let mut same = IndexMap::new();
+        for obj in version_tree.objects() {
+            let Some(prev) = previous.get(&relative_path(module, obj)) else {
+                continue;
+            };
+            let prev_shape = Shape::new(prev, resolver, |path| {
+                unprefixed(prev_module, path).to_owned()
+            });
+            if prev_shape != Shape::new(obj, resolver, |path| unprefixed(module, path).to_owned()) {
+                continue;
+            }
+            let prev_path = SchemaType::from(*prev).path();
+            let target = result.get(&prev_path).cloned().unwrap_or(prev_path);
+            same.insert(SchemaType::from(obj).path(), (obj, target));
+        }
+
+        // Drop those that refer to a type of this version that changed,
+        // until none do
+        let version_prefix = format!("{}::", module);
+        loop {
+            let changed: Vec<String> = same
+                .iter()
+                .filter(|(_, (obj, _))| {
+                    references(obj, resolver)
+                        .iter()
+                        .any(|path| path.starts_with(&version_prefix) && !same.contains_key(path))
+                })
+                .map(|(path, _)| path.to_owned())
+                .collect();
+            if changed.is_empty() {
+                break;
+            }
+            for path in changed {
+                same.shift_remove(&path);
+            }
+        }
+
+        result.extend(same.into_iter().map(|(path, (_, target))| (path, target)));
+    }
+    result
+}
+
+/// The paths of the types an object refers to
+fn references(obj: &SchemaObject, resolver: &Resolver) -> Vec<String> {
+    let mut type_refs = vec![TypeRef::from_ref(obj.type_ref.clone())];
+    type_refs.extend(
+        obj.props
+            .iter()
+            .flatten()
+            .map(|(_, prop)| prop.type_ref.clone()),
+    );
+    type_refs
+        .iter()
+        .filter_map(|type_ref| resolver.resolve(type_ref))
+        .map(|schema_type| schema_type.path())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Property;
+
+    fn version_ir(version: &str, metadata_props: &[&str]) -> Ir {
+        let mut resolver = Resolver::new();
+        resolver.add_type("#metadata", SchemaType::new("metadata", "Metadata"));
+        resolver.add_type("#catalog", SchemaType::new("catalog", "Catalog"));
+        resolver.add_type("#group", SchemaType::new("group", "Group"));
+
+        let object = |ns: &str, name: &str, props: &[(&str, &str)]| SchemaObject {
+            id: Some(format!("#{}", ns)),
+            _type: Some("object".to_owned()),
+            props: Some(
+                props
+                    .iter()
+                    .map(|(name, _ref)| {
+                        (name.to_string(), Property::test_property(name, name, _ref))
+                    })
+                    .collect(),
+            ),
+            ..SchemaObject::test_object(ns, name)
+        };
+
+        let mut schema = Schema::new("", version, "");
+        let metadata_props: Vec<(&str, &str)> = metadata_props
+            .iter()
+            .map(|name| (*name, "#/definitions/StringDatatype"))
+            .collect();
+        for obj in [
+            object("metadata", "Metadata", &metadata_props),
+            object("catalog", "Catalog", &[("metadata", "#metadata")]),
+            object("group", "Group", &[("id", "#/definitions/TokenDatatype")]),
+        ] {
+            schema.add_object(&obj.ns.clone(), &obj).expect("oops");
+        }
+        schema.documents.insert(
+            "catalog".to_owned(),
+            TypeRef::from_ref(Some("#catalog".to_owned())),
+        );
+        Ir { schema, resolver }
+    }
+
+    #[test]
+    fn test_merge_versions() {
+        let ir = merge_versions(vec![
+            version_ir("1.0.4", &["title"]),
+            version_ir("1.0.6", &["title"]),
+            version_ir("1.1.2", &["title", "version"]),
+        ])
+        .expect("oops");
+
+        assert_eq!(ir.schema.version, "1.1.2");
+        let versions: Vec<&str> = ir
+            .schema
+            .version_trees()
+            .iter()
+            .map(|(module, _)| *module)
+            .collect();
+        assert_eq!(versions, vec!["v1_0_4", "v1_0_6", "v1_1_2"]);
+
+        let catalog = &ir.schema.version_trees()[1].1.documents["catalog"];
+        assert_eq!(
+            ir.resolver.resolve(catalog),
+            Some(SchemaType::new("v1_0_6::catalog", "Catalog"))
+        );
+        let group = ir.schema.objects()[2];
+        assert_eq!(group.ns, "v1_0_4::group");
+        assert_eq!(
+            ir.resolver
+                .resolve(&group.props.as_ref().expect("oops")["id"].type_ref),
+            Some(SchemaType::new("oscal_types", "TokenDatatype"))
+        );
+
+        assert!(merge_versions(vec![version_ir("1.0.4", &[]), version_ir("1.0.4", &[])]).is_err());
+    }
+
+    #[test]
+    fn test_shared_types() {
+        let ir = merge_versions(vec![
+            version_ir("1.0.4", &["title"]),
+            version_ir("1.0.6", &["title"]),
+            version_ir("1.1.2", &["title", "version"]),
+        ])
+        .expect("oops");
+
+        let shared = shared_types(&ir.schema, &ir.resolver);
+        assert_eq!(
+            shared.into_iter().collect::<Vec<_>>(),
+            vec![
+                (
+                    "v1_0_6::metadata::Metadata".to_owned(),
+                    "v1_0_4::metadata::Metadata".to_owned()
+                ),
+                (
+                    "v1_0_6::catalog::Catalog".to_owned(),
+                    "v1_0_4::catalog::Catalog".to_owned()
+                ),
+                (
+                    "v1_0_6::group::Group".to_owned(),
+                    "v1_0_4::group::Group".to_owned()
+                ),
+                // Catalog changed with Metadata
+                (
+                    "v1_1_2::group::Group".to_owned(),
+                    "v1_0_4::group::Group".to_owned()
+                ),
+            ]
+        );
+    }
+}
@@ -2,6 +2,14 @@ use thiserror::Error;
 
 #[derive(Debug, Clone, Error)]
 pub enum Error {
-    #[error("Type Error")]
+    #[error("Type Error: {0}")]
     TypeError(#[from] oscal_types::Error),
+    #[error("Parse error: {0}")]
+    Parse(String),
+    #[error("Invalid document at {path}: {message}")]
+    Deserialize { path: String, message: String },
+    #[error("Unsupported OSCAL version: {0}")]
+    UnsupportedVersion(String),
+    #[error("Can't upgrade {0}")]
+    Upgrade(String),
 }